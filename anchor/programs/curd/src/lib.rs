@@ -1,245 +1,3761 @@
 #![allow(clippy::result_large_err)]
+// Anchor 0.31's generated IDL instructions still call `AccountInfo::realloc`,
+// which solana-account-info 2.3 deprecates in favour of `resize`.
+#![allow(deprecated)]
 
 use anchor_lang::prelude::*;
-use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use anchor_lang::system_program::{
+    allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
 
 declare_id!("FpsfKtSsRGMnZBWd6mF3yVQdpWWJx5btjygt8YNAUTj3");
 
-const SPIN_COOLDOWN_SECS: i64 = 7 * 24 * 60 * 60;
+const MIN_COOLDOWN_SECS: i64 = 60 * 60;
+const MAX_COOLDOWN_SECS: i64 = 30 * 24 * 60 * 60;
+const MIN_CLAIM_WINDOW_SECS: i64 = 24 * 60 * 60;
+const MAX_CLAIM_WINDOW_SECS: i64 = 90 * 24 * 60 * 60;
+const MIN_DRAW_TIMEOUT_SECS: i64 = 24 * 60 * 60;
+const MAX_DRAW_TIMEOUT_SECS: i64 = 30 * 24 * 60 * 60;
+const MAX_CREATOR_FEE_BPS: u16 = 2_000;
+const MAX_TITLE_LEN: usize = 50;
+const MAX_IPFS_HASH_LEN: usize = 100;
+const MAX_HANDLE_LEN: usize = 32;
+const MAX_BIO_LEN: usize = 160;
+const MAX_SPONSOR_NAME_LEN: usize = 64;
+const MAX_TAGS: usize = 5;
+const MAX_TAG_LEN: usize = 32;
+const TAG_PAGE_SIZE: usize = 32;
+const MAX_COMMENT_LEN: usize = 280;
+const REACTION_KIND_COUNT: usize = 5;
+const MAX_TIP_FEE_BPS: u16 = 1_000;
+const BPS_DENOMINATOR: u64 = 10_000;
+const NO_ROUND: u64 = u64::MAX;
+const MAX_CRANKS: usize = 5;
+const MAX_BENEFICIARIES: usize = 5;
+const PRIZE_TIERS: usize = 3;
+// declare_winner takes an entry and a prize claim per prize, plus any void
+// tickets it skips, so more winners won't fit in one legacy transaction
+const MAX_CONSOLATION_WINNERS: u8 = 3;
+// lock_draw picks a slot past the current leader's turn, so whoever lands the
+// lock can't also produce the slot hash it will be mixed with
+const REVEAL_DELAY_SLOTS: u64 = 8;
+// slots the SlotHashes sysvar keeps; past this a locked draw can't be revealed
+const SLOT_HASHES_DEPTH: u64 = 512;
 
 #[program]
 pub mod counter {
     use super::*;
 
-    pub fn initialize_pool(ctx: Context<InitializePool>) -> Result<()> {
+    pub fn initialize_pool(
+        ctx: Context<InitializePool>,
+        pool_id: u64,
+        params: PoolConfigParams,
+    ) -> Result<()> {
+        validate_config(&params)?;
+        apply_config(&mut ctx.accounts.pool_config, &params);
+
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.weekly_pool;
+        init_pool(pool, pool_id, ctx.accounts.creator.key(), None, now);
+
+        open_round(&mut ctx.accounts.first_round, pool.key(), 0, now);
+        init_treasury(&mut ctx.accounts.treasury, pool.key());
+        Ok(())
+    }
+
+    /// Same as initialize_pool, but contributions and prizes are paid in
+    /// `mint` (SPL Token or Token-2022) and held in a vault PDA owned by the
+    /// pool. Contribution limits are then in the mint's base units. Token-2022
+    /// mints with a transfer fee, transfer hook or permanent delegate are
+    /// refused, since the vault would no longer hold what the pool records.
+    pub fn initialize_token_pool(
+        ctx: Context<InitializeTokenPool>,
+        pool_id: u64,
+        params: PoolConfigParams,
+    ) -> Result<()> {
+        validate_config(&params)?;
+        validate_mint(&ctx.accounts.mint.to_account_info())?;
+        apply_config(&mut ctx.accounts.pool_config, &params);
+
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.weekly_pool;
+        init_pool(
+            pool,
+            pool_id,
+            ctx.accounts.creator.key(),
+            Some(ctx.accounts.mint.key()),
+            now,
+        );
+
+        open_round(&mut ctx.accounts.first_round, pool.key(), 0, now);
+        init_treasury(&mut ctx.accounts.treasury, pool.key());
+        Ok(())
+    }
+
+    /// Commits to the seed for the current round's draw as `sha256(seed)`.
+    /// Only allowed before the round's first entry, and entries are refused
+    /// until it is set. The seed alone still doesn't fix the outcome: the
+    /// draw also mixes in the slot hash picked by lock_draw, which nobody
+    /// knows while entries are open.
+    pub fn commit_draw(ctx: Context<CommitDraw>, commitment: [u8; 32]) -> Result<()> {
+        let round = &mut ctx.accounts.round;
+        require!(
+            round.draw_commitment.is_none(),
+            CustomError::CommitmentAlreadySet
+        );
+        require!(round.ticket_count == 0, CustomError::CommitAfterEntries);
+
+        round.draw_commitment = Some(commitment);
+        Ok(())
+    }
+
+    /// Fixes the slot whose hash declare_winner mixes into the draw, a few
+    /// slots after this one. Only the pool authority can lock, once entries
+    /// are locked. declare_winner has to land while that hash is still in the
+    /// SlotHashes sysvar (about 512 slots); after that anyone can cancel the
+    /// round.
+    pub fn lock_draw(ctx: Context<LockDraw>) -> Result<()> {
+        let round = &mut ctx.accounts.round;
+        require!(round.draw_commitment.is_some(), CustomError::NoCommitment);
+        require!(round.reveal_slot == 0, CustomError::DrawAlreadyLocked);
+        require!(
+            entries_locked(
+                &ctx.accounts.weekly_pool,
+                &ctx.accounts.pool_config,
+                Clock::get()?.unix_timestamp
+            ),
+            CustomError::EntriesStillOpen
+        );
+
+        round.reveal_slot = Clock::get()?
+            .slot
+            .checked_add(REVEAL_DELAY_SLOTS)
+            .ok_or(CustomError::MathOverflow)?;
+        Ok(())
+    }
+
+    // Cranks may commit and declare draws alongside the creator.
+    pub fn add_crank(ctx: Context<ManageCranks>, crank: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.weekly_pool;
+        require!(
+            !pool.cranks.contains(&crank),
+            CustomError::CrankAlreadyAdded
+        );
+        require!(pool.cranks.len() < MAX_CRANKS, CustomError::TooManyCranks);
+        pool.cranks.push(crank);
+        Ok(())
+    }
+
+    pub fn remove_crank(ctx: Context<ManageCranks>, crank: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.weekly_pool;
+        let position = pool
+            .cranks
+            .iter()
+            .position(|key| *key == crank)
+            .ok_or(CustomError::CrankNotFound)?;
+        pool.cranks.remove(position);
+        Ok(())
+    }
+
+    // Takes effect immediately, including for the round that is open, but not
+    // once that round is locked: a new cooldown could reopen entries and new
+    // draw thresholds could roll over a draw whose outcome is already known.
+    pub fn update_config(ctx: Context<UpdateConfig>, params: PoolConfigParams) -> Result<()> {
+        require!(
+            ctx.accounts.round.reveal_slot == 0
+                && !entries_locked(
+                    &ctx.accounts.weekly_pool,
+                    &ctx.accounts.pool_config,
+                    Clock::get()?.unix_timestamp
+                ),
+            CustomError::RoundLocked
+        );
+        validate_config(&params)?;
+        apply_config(&mut ctx.accounts.pool_config, &params);
+        Ok(())
+    }
+
+    /// Sets how withdraw_fees splits the treasury. Weights are basis points
+    /// and must add up to 10_000; an empty list pays everything to the
+    /// creator.
+    pub fn set_beneficiaries(
+        ctx: Context<SetBeneficiaries>,
+        beneficiaries: Vec<Beneficiary>,
+    ) -> Result<()> {
+        require!(
+            beneficiaries.len() <= MAX_BENEFICIARIES,
+            CustomError::InvalidBeneficiaries
+        );
+        if !beneficiaries.is_empty() {
+            require!(
+                beneficiaries.iter().all(|b| b.bps > 0)
+                    && beneficiaries.iter().map(|b| b.bps as u64).sum::<u64>() == BPS_DENOMINATOR,
+                CustomError::InvalidBeneficiaries
+            );
+        }
+
+        ctx.accounts.treasury.beneficiaries = beneficiaries;
+        Ok(())
+    }
+
+    /// Pays out everything the treasury has accrued. The remaining accounts
+    /// are the destinations in beneficiary order, or just the creator's when
+    /// no beneficiaries are set: wallets for SOL pools, token accounts owned
+    /// by those wallets for token pools. Rounding dust goes to the last one.
+    pub fn withdraw_fees<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawFees<'info>>,
+    ) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        let amount = treasury.accrued;
+        require!(amount > 0, CustomError::NoFeesAccrued);
+
+        let mut payees: Vec<(Pubkey, u64)> = if treasury.beneficiaries.is_empty() {
+            vec![(ctx.accounts.creator.key(), amount)]
+        } else {
+            treasury
+                .beneficiaries
+                .iter()
+                .map(|b| (b.wallet, apply_bps(amount, b.bps)))
+                .collect()
+        };
+        let paid: u64 = payees.iter().map(|(_, share)| share).sum();
+        if let Some((_, last_share)) = payees.last_mut() {
+            *last_share = amount
+                .checked_sub(paid)
+                .and_then(|dust| last_share.checked_add(dust))
+                .ok_or(CustomError::MathOverflow)?;
+        }
+
+        treasury.accrued = 0;
+        treasury.total_withdrawn = treasury
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let treasury_info = treasury.to_account_info();
+        let pool = &ctx.accounts.weekly_pool;
+        for (i, (wallet, share)) in payees.iter().enumerate() {
+            let destination = ctx
+                .remaining_accounts
+                .get(i)
+                .ok_or(CustomError::InvalidBeneficiaries)?;
+            if pool.mint.is_none() {
+                require_keys_eq!(
+                    destination.key(),
+                    *wallet,
+                    CustomError::InvalidBeneficiaries
+                );
+                pay_from_pool(&treasury_info, destination, *share)?;
+            } else {
+                let token_account = InterfaceAccount::<TokenAccount>::try_from(destination)?;
+                require_keys_eq!(
+                    token_account.owner,
+                    *wallet,
+                    CustomError::InvalidBeneficiaries
+                );
+                pay_from_vault(
+                    pool,
+                    ctx.bumps.weekly_pool,
+                    &ctx.accounts.mint,
+                    &ctx.accounts.vault,
+                    &Some(token_account),
+                    &ctx.accounts.token_program,
+                    *share,
+                )?;
+            }
+        }
+
+        msg!("Withdrew {} in fees to {} payees", amount, payees.len());
+        Ok(())
+    }
+
+    pub fn create_profile(
+        ctx: Context<CreateProfile>,
+        bio: String,
+        avatar_cid: String,
+    ) -> Result<()> {
+        validate_profile(&bio, &avatar_cid)?;
+
+        // handles are only assigned through the registry, see claim_handle
+        let profile = &mut ctx.accounts.author_profile;
+        profile.owner = *ctx.accounts.owner.key;
+        profile.handle = String::new();
+        profile.bio = bio;
+        profile.avatar_cid = avatar_cid;
+        profile.post_count = 0;
+        profile.total_tips_sent = 0;
+        profile.total_winnings = 0;
+        profile.total_contributed = 0;
+        Ok(())
+    }
+
+    pub fn update_profile(
+        ctx: Context<UpdateProfile>,
+        bio: String,
+        avatar_cid: String,
+    ) -> Result<()> {
+        validate_profile(&bio, &avatar_cid)?;
+
+        // counters are maintained by the entry and draw instructions only
+        let profile = &mut ctx.accounts.author_profile;
+        profile.bio = bio;
+        profile.avatar_cid = avatar_cid;
+        Ok(())
+    }
+
+    pub fn claim_handle(ctx: Context<ClaimHandle>, handle: String) -> Result<()> {
+        validate_handle(&handle)?;
+
+        let profile = &mut ctx.accounts.author_profile;
+        require!(profile.handle.is_empty(), CustomError::HandleAlreadySet);
+        profile.handle = handle.clone();
+
+        let registry = &mut ctx.accounts.handle_registry;
+        registry.owner = *ctx.accounts.owner.key;
+        registry.handle = handle;
+        registry.claimed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn release_handle(ctx: Context<ReleaseHandle>, _handle: String) -> Result<()> {
+        // the registry account is closed by the accounts constraint
+        ctx.accounts.author_profile.handle = String::new();
+        Ok(())
+    }
+
+    pub fn transfer_handle(ctx: Context<TransferHandle>, handle: String) -> Result<()> {
+        let new_profile = &mut ctx.accounts.new_owner_profile;
+        require!(new_profile.handle.is_empty(), CustomError::HandleAlreadySet);
+        new_profile.handle = handle;
+
+        ctx.accounts.author_profile.handle = String::new();
+
+        let registry = &mut ctx.accounts.handle_registry;
+        registry.owner = new_profile.owner;
+        registry.claimed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn create_blog_entry(
+        ctx: Context<CreateEntry>,
+        title: String,
+        ipfs_hash: String,
+        pool_contribution: u64, // lamports (or mint base units) contributed to the pot
+    ) -> Result<()> {
+        require!(title.len() <= MAX_TITLE_LEN, CustomError::TitleTooLong);
+        require!(
+            ipfs_hash.len() <= MAX_IPFS_HASH_LEN,
+            CustomError::IpfsHashTooLong
+        );
+        let config = &ctx.accounts.pool_config;
+        require!(
+            pool_contribution >= config.min_contribution
+                && pool_contribution <= config.max_contribution,
+            CustomError::ContributionOutOfRange
+        );
+        require!(
+            !entries_locked(
+                &ctx.accounts.weekly_pool,
+                config,
+                Clock::get()?.unix_timestamp
+            ),
+            CustomError::RoundLocked
+        );
+        // the seed has to be committed before anyone enters, see commit_draw
+        require!(
+            ctx.accounts.round.draw_commitment.is_some(),
+            CustomError::NoCommitment
+        );
+        // once the reveal slot is fixed a new entry could steer the draw
+        require!(
+            ctx.accounts.round.reveal_slot == 0,
+            CustomError::RoundLocked
+        );
+
+        // the entry PDA was derived from the current post_count, so claim it
+        let profile = &mut ctx.accounts.author_profile;
+        let post_index = profile.post_count;
+        profile.post_count = post_index.checked_add(1).ok_or(CustomError::MathOverflow)?;
+        profile.total_contributed = profile
+            .total_contributed
+            .checked_add(pool_contribution)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let blog_entry = &mut ctx.accounts.blog_entry;
+        let owner = &ctx.accounts.owner;
+        blog_entry.owner = *owner.key;
+        blog_entry.post_index = post_index;
+        blog_entry.title = title.clone();
+        blog_entry.ipfs_hash = ipfs_hash;
+        blog_entry.random_number = (Clock::get()?.unix_timestamp % 90000 + 10000) as u32; // 5-digit randomish number
+        blog_entry.created_at = Clock::get()?.unix_timestamp;
+        blog_entry.tip = pool_contribution;
+        blog_entry.updated_at = blog_entry.created_at;
+        blog_entry.edit_count = 0;
+        blog_entry.next_comment_index = 0;
+        blog_entry.comment_count = 0;
+        blog_entry.reaction_counts = [0; REACTION_KIND_COUNT];
+        blog_entry.total_tips_received = 0;
+        blog_entry.refunded = false;
+
+        // transfer SOL to weekly pool, or tokens to its vault
+        let lamports = pool_contribution;
+
+        deposit_to_pool(
+            &ctx.accounts.weekly_pool,
+            owner,
+            &ctx.accounts.system_program,
+            &ctx.accounts.mint,
+            &ctx.accounts.owner_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.token_program,
+            lamports,
+        )?;
+
+        // increment total pool and hand out the entry's range of tickets
+        let pool = &mut ctx.accounts.weekly_pool;
+        pool.total_pool = pool
+            .total_pool
+            .checked_add(lamports)
+            .ok_or(CustomError::MathOverflow)?;
+        pool.total_entries = pool
+            .total_entries
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+        pool.round_contributions = pool
+            .round_contributions
+            .checked_add(lamports)
+            .ok_or(CustomError::MathOverflow)?;
+
+        // a matching campaign moves its match from escrow into the pot; it
+        // adds to the prize, not to the author's tickets
+        if let Some(campaign) = ctx.accounts.campaign.as_mut() {
+            let campaign_match = ctx
+                .accounts
+                .campaign_match
+                .as_mut()
+                .ok_or(CustomError::InvalidCampaign)?;
+            require!(
+                Clock::get()?.unix_timestamp < campaign.ends_at,
+                CustomError::CampaignEnded
+            );
+
+            let matched = pool_contribution
+                .min(
+                    campaign
+                        .per_author_cap
+                        .saturating_sub(campaign_match.matched),
+                )
+                .min(campaign.remaining);
+            campaign_match.campaign = campaign.key();
+            campaign_match.author = ctx.accounts.owner.key();
+            campaign_match.matched = campaign_match
+                .matched
+                .checked_add(matched)
+                .ok_or(CustomError::MathOverflow)?;
+            campaign.remaining = campaign
+                .remaining
+                .checked_sub(matched)
+                .ok_or(CustomError::MathOverflow)?;
+            campaign.matched_total = campaign
+                .matched_total
+                .checked_add(matched)
+                .ok_or(CustomError::MathOverflow)?;
+
+            pool.reserved_matching = pool
+                .reserved_matching
+                .checked_sub(matched)
+                .ok_or(CustomError::MathOverflow)?;
+            pool.total_pool = pool
+                .total_pool
+                .checked_add(matched)
+                .ok_or(CustomError::MathOverflow)?;
+        }
+
+        let tickets = tickets_for(&ctx.accounts.pool_config, pool_contribution);
+        let round = &mut ctx.accounts.round;
+        let blog_entry = &mut ctx.accounts.blog_entry;
+        blog_entry.pool = pool.key();
+        blog_entry.round_id = round.round_id;
+        blog_entry.ticket_start = round.ticket_count;
+        blog_entry.tickets = tickets;
+        round.ticket_count = round
+            .ticket_count
+            .checked_add(tickets)
+            .ok_or(CustomError::MathOverflow)?;
+
+        // every entry is mixed into the draw so the committed seed alone
+        // doesn't determine the outcome
+        round.entropy = hashv(&[
+            &round.entropy,
+            blog_entry.key().as_ref(),
+            &Clock::get()?.slot.to_le_bytes(),
+        ])
+        .to_bytes();
+
+        Ok(())
+    }
+
+    pub fn update_blog_entry(
+        ctx: Context<UpdateEntry>,
+        title: String,
+        new_ipfs_hash: String,
+    ) -> Result<()> {
+        require!(title.len() <= MAX_TITLE_LEN, CustomError::TitleTooLong);
+        require!(
+            new_ipfs_hash.len() <= MAX_IPFS_HASH_LEN,
+            CustomError::IpfsHashTooLong
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let blog_entry = &mut ctx.accounts.blog_entry;
+
+        // snapshot the content being replaced; revision N holds the hash
+        // that was live before the (N + 1)th edit
+        let revision = &mut ctx.accounts.revision;
+        revision.entry = blog_entry.key();
+        revision.index = blog_entry.edit_count;
+        revision.title = blog_entry.title.clone();
+        revision.ipfs_hash = blog_entry.ipfs_hash.clone();
+        revision.editor = ctx.accounts.owner.key();
+        revision.timestamp = now;
+
+        // random_number and tip are left alone so the entry's draw odds and
+        // pool contribution stay exactly as they were when it was published
+        blog_entry.title = title;
+        blog_entry.ipfs_hash = new_ipfs_hash;
+        blog_entry.updated_at = now;
+        blog_entry.edit_count = blog_entry
+            .edit_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn create_tag(ctx: Context<CreateTag>, tag: String) -> Result<()> {
+        validate_tag(&tag)?;
+
+        let tag_state = &mut ctx.accounts.tag_state;
+        tag_state.tag = tag.clone();
+        tag_state.current_page = 0;
+        tag_state.entry_count = 0;
+
+        let first_page = &mut ctx.accounts.first_page;
+        first_page.tag = tag;
+        first_page.page_index = 0;
+        first_page.entries = Vec::new();
+        Ok(())
+    }
+
+    // Only needed once the current page is full; anyone may pay for it.
+    pub fn add_tag_page(ctx: Context<AddTagPage>, _tag: String) -> Result<()> {
+        require!(
+            ctx.accounts.current_page.entries.len() >= TAG_PAGE_SIZE,
+            CustomError::TagPageNotFull
+        );
+
+        let tag_state = &mut ctx.accounts.tag_state;
+        tag_state.current_page = tag_state
+            .current_page
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let new_page = &mut ctx.accounts.new_page;
+        new_page.tag = tag_state.tag.clone();
+        new_page.page_index = tag_state.current_page;
+        new_page.entries = Vec::new();
+        Ok(())
+    }
+
+    pub fn tag_blog_entry(ctx: Context<TagEntry>, tag: String) -> Result<()> {
+        let blog_entry = &mut ctx.accounts.blog_entry;
+        require!(blog_entry.tags.len() < MAX_TAGS, CustomError::TooManyTags);
+        require!(
+            !blog_entry.tags.contains(&tag),
+            CustomError::TagAlreadyAdded
+        );
+
+        let page = &mut ctx.accounts.tag_page;
+        require!(page.entries.len() < TAG_PAGE_SIZE, CustomError::TagPageFull);
+        page.entries.push(blog_entry.key());
+        blog_entry.tags.push(tag);
+
+        let tag_state = &mut ctx.accounts.tag_state;
+        tag_state.entry_count = tag_state
+            .entry_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn untag_blog_entry(ctx: Context<UntagEntry>, tag: String, _page_index: u32) -> Result<()> {
+        let blog_entry = &mut ctx.accounts.blog_entry;
+        let key = blog_entry.key();
+
+        let page = &mut ctx.accounts.tag_page;
+        let position = page
+            .entries
+            .iter()
+            .position(|entry| *entry == key)
+            .ok_or(CustomError::TagNotFound)?;
+        page.entries.swap_remove(position);
+        blog_entry.tags.retain(|t| *t != tag);
+
+        let tag_state = &mut ctx.accounts.tag_state;
+        tag_state.entry_count = tag_state.entry_count.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Closes an entry and returns its rent to the owner.
+    ///
+    /// The `pool_contribution` paid at creation is forfeited: it stays in the
+    /// weekly pool and is paid out with the next draw. If the entry joined the
+    /// currently open round it is also removed from `total_entries`, and
+    /// `void_ticket` must be passed to record that its tickets can no longer
+    /// win; declare_winner skips over them.
+    pub fn delete_blog_entry(ctx: Context<DeleteEntry>) -> Result<()> {
+        let blog_entry = &ctx.accounts.blog_entry;
+        // tag pages would otherwise keep pointing at the closed entry
+        require!(blog_entry.tags.is_empty(), CustomError::EntryStillTagged);
+        let pool = &mut ctx.accounts.weekly_pool;
+
+        // rounds from before round accounts existed were never created
+        let round = load_if_open::<RoundState>(&ctx.accounts.round)?;
+        if let Some(round) = &round {
+            // the tip stays in reserved_refunds until its owner takes it back
+            require!(
+                !round.cancelled || blog_entry.refunded,
+                CustomError::RefundPending
+            );
+        }
+
+        if blog_entry.round_id == pool.round_id {
+            // voiding a range after the lock could steer the draw
+            let round = round.ok_or(CustomError::RoundRequired)?;
+            require!(
+                round.reveal_slot == 0
+                    && !entries_locked(
+                        pool,
+                        &ctx.accounts.pool_config,
+                        Clock::get()?.unix_timestamp
+                    ),
+                CustomError::RoundLocked
+            );
+            pool.total_entries = pool.total_entries.saturating_sub(1);
+            // forfeited, so a cancelled round won't refund it
+            pool.round_contributions = pool.round_contributions.saturating_sub(blog_entry.tip);
+
+            let void_ticket = ctx
+                .accounts
+                .void_ticket
+                .as_mut()
+                .ok_or(CustomError::VoidTicketRequired)?;
+            void_ticket.pool = pool.key();
+            void_ticket.round_id = pool.round_id;
+            void_ticket.ticket_start = blog_entry.ticket_start;
+            void_ticket.ticket_end = blog_entry
+                .ticket_start
+                .checked_add(blog_entry.tickets)
+                .ok_or(CustomError::MathOverflow)?;
+        }
+
+        msg!(
+            "Deleted blog: {:?}, forfeited contribution: {}",
+            blog_entry.title,
+            blog_entry.tip
+        );
+
+        Ok(())
+    }
+
+    pub fn post_comment(ctx: Context<PostComment>, body: String, body_cid: String) -> Result<()> {
+        validate_comment(&body, &body_cid)?;
+
+        let blog_entry = &mut ctx.accounts.blog_entry;
+        let entry_key = blog_entry.key();
+
+        let parent = match ctx.accounts.parent_comment.as_mut() {
+            Some(parent) => {
+                require_keys_eq!(parent.entry, entry_key, CustomError::InvalidParentComment);
+                parent.reply_count = parent
+                    .reply_count
+                    .checked_add(1)
+                    .ok_or(CustomError::MathOverflow)?;
+                Some(parent.index)
+            }
+            None => None,
+        };
+
+        let now = Clock::get()?.unix_timestamp;
+        let comment = &mut ctx.accounts.comment;
+        comment.entry = entry_key;
+        comment.author = *ctx.accounts.author.key;
+        comment.index = blog_entry.next_comment_index;
+        comment.parent = parent;
+        comment.body = body;
+        comment.body_cid = body_cid;
+        comment.created_at = now;
+        comment.edited_at = now;
+        comment.reply_count = 0;
+
+        blog_entry.next_comment_index = blog_entry
+            .next_comment_index
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+        blog_entry.comment_count = blog_entry
+            .comment_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn edit_comment(ctx: Context<EditComment>, body: String, body_cid: String) -> Result<()> {
+        validate_comment(&body, &body_cid)?;
+
+        let comment = &mut ctx.accounts.comment;
+        comment.body = body;
+        comment.body_cid = body_cid;
+        comment.edited_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Replies keep their parent index, so clients should render a missing
+    // parent as deleted rather than dropping the whole thread.
+    // The entry or parent may already be deleted, in which case there's no
+    // count to fix.
+    pub fn delete_comment(ctx: Context<DeleteComment>) -> Result<()> {
+        update_if_open(
+            &ctx.accounts.blog_entry,
+            |blog_entry: &mut BlogEntryState| {
+                blog_entry.comment_count = blog_entry.comment_count.saturating_sub(1);
+            },
+        )?;
+
+        let comment = &ctx.accounts.comment;
+        if let Some(parent_index) = comment.parent {
+            let parent = ctx
+                .accounts
+                .parent_comment
+                .as_ref()
+                .ok_or(CustomError::InvalidParentComment)?;
+            let (parent_key, _) = Pubkey::find_program_address(
+                &[
+                    b"comment",
+                    comment.entry.as_ref(),
+                    &parent_index.to_le_bytes(),
+                ],
+                &crate::ID,
+            );
+            require_keys_eq!(parent.key(), parent_key, CustomError::InvalidParentComment);
+            update_if_open(parent, |parent: &mut CommentState| {
+                parent.reply_count = parent.reply_count.saturating_sub(1);
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn react(ctx: Context<React>, kind: ReactionKind) -> Result<()> {
+        let reaction = &mut ctx.accounts.reaction;
+        reaction.entry = ctx.accounts.blog_entry.key();
+        reaction.reactor = *ctx.accounts.reactor.key;
+        reaction.kind = kind;
+        reaction.created_at = Clock::get()?.unix_timestamp;
+
+        let count = &mut ctx.accounts.blog_entry.reaction_counts[kind as usize];
+        *count = count.checked_add(1).ok_or(CustomError::MathOverflow)?;
+        Ok(())
+    }
+
+    // To switch reaction kind a wallet unreacts first and reacts again.
+    // Works after the entry is deleted, so reactors can still reclaim rent.
+    pub fn unreact(ctx: Context<Unreact>) -> Result<()> {
+        let kind = ctx.accounts.reaction.kind;
+        update_if_open(
+            &ctx.accounts.blog_entry,
+            |blog_entry: &mut BlogEntryState| {
+                let count = &mut blog_entry.reaction_counts[kind as usize];
+                *count = count.saturating_sub(1);
+            },
+        )
+    }
+
+    /// Sends `amount` lamports from any reader to the entry's owner. The
+    /// pool config's `tip_fee_bps` share of it goes to the entry's pool instead.
+    pub fn tip_author(ctx: Context<TipAuthor>, amount: u64) -> Result<()> {
+        require!(amount > 0, CustomError::InvalidTipAmount);
+
+        let (author_share, platform_cut) = split_tip(
+            ctx.accounts.weekly_pool.mint,
+            ctx.accounts.pool_config.tip_fee_bps,
+            amount,
+        );
+
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.tipper.to_account_info(),
+                    to: ctx.accounts.author.to_account_info(),
+                },
+            ),
+            author_share,
+        )?;
+
+        if platform_cut > 0 {
+            transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.tipper.to_account_info(),
+                        to: ctx.accounts.weekly_pool.to_account_info(),
+                    },
+                ),
+                platform_cut,
+            )?;
+
+            let pool = &mut ctx.accounts.weekly_pool;
+            pool.total_pool = pool
+                .total_pool
+                .checked_add(platform_cut)
+                .ok_or(CustomError::MathOverflow)?;
+        }
+
+        let blog_entry = &mut ctx.accounts.blog_entry;
+        blog_entry.total_tips_received = blog_entry
+            .total_tips_received
+            .checked_add(author_share)
+            .ok_or(CustomError::MathOverflow)?;
+
+        if let Some(profile) = ctx.accounts.tipper_profile.as_mut() {
+            profile.total_tips_sent = profile
+                .total_tips_sent
+                .checked_add(amount)
+                .ok_or(CustomError::MathOverflow)?;
+        }
+
+        emit!(AuthorTipped {
+            entry: blog_entry.key(),
+            author: blog_entry.owner,
+            tipper: ctx.accounts.tipper.key(),
+            amount,
+            platform_cut,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Adds `amount` to the pool's pot without creating an entry, so it
+    /// doesn't count toward `total_entries` and is never refunded by
+    /// cancel_round. The deposit is recorded in a Sponsorship account so the
+    /// sponsor can be credited for the round it landed in.
+    pub fn sponsor_pool(
+        ctx: Context<SponsorPool>,
+        amount: u64,
+        name: String,
+        cid: String,
+    ) -> Result<()> {
+        require!(amount > 0, CustomError::InvalidDepositAmount);
+        validate_sponsorship(&name, &cid)?;
+
+        let sponsor = &ctx.accounts.sponsor;
+        deposit_to_pool(
+            &ctx.accounts.weekly_pool,
+            sponsor,
+            &ctx.accounts.system_program,
+            &ctx.accounts.mint,
+            &ctx.accounts.sponsor_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+
+        let pool = &mut ctx.accounts.weekly_pool;
+        pool.total_pool = pool
+            .total_pool
+            .checked_add(amount)
+            .ok_or(CustomError::MathOverflow)?;
+        let sponsorship_index = pool.sponsorship_count;
+        pool.sponsorship_count = sponsorship_index
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let sponsorship = &mut ctx.accounts.sponsorship;
+        sponsorship.pool = pool.key();
+        sponsorship.index = sponsorship_index;
+        sponsorship.round_id = pool.round_id;
+        sponsorship.sponsor = sponsor.key();
+        sponsorship.amount = amount;
+        sponsorship.name = name;
+        sponsorship.cid = cid;
+        sponsorship.created_at = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "{:?} sponsored round {} with {}",
+            sponsorship.name,
+            sponsorship.round_id,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Escrows `amount` in the pool to match author contributions 1:1 until
+    /// `ends_at`. Each author is matched up to `per_author_cap` in total, and
+    /// matching stops once the escrow runs out. Entries opt in by passing the
+    /// campaign to create_blog_entry.
+    pub fn create_campaign(
+        ctx: Context<CreateCampaign>,
+        amount: u64,
+        per_author_cap: u64,
+        ends_at: i64,
+    ) -> Result<()> {
+        require!(amount > 0, CustomError::InvalidDepositAmount);
+        require!(
+            per_author_cap > 0 && ends_at > Clock::get()?.unix_timestamp,
+            CustomError::InvalidCampaign
+        );
+
+        deposit_to_pool(
+            &ctx.accounts.weekly_pool,
+            &ctx.accounts.sponsor,
+            &ctx.accounts.system_program,
+            &ctx.accounts.mint,
+            &ctx.accounts.sponsor_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+
+        // held apart from total_pool until it is matched
         let pool = &mut ctx.accounts.weekly_pool;
-        pool.total_pool = 0;
-        pool.total_entries = 0;
-        pool.last_winner_number = 0;
-        pool.last_spin_timestamp = 0;
-        pool.creator = *ctx.accounts.creator.key;
+        pool.reserved_matching = pool
+            .reserved_matching
+            .checked_add(amount)
+            .ok_or(CustomError::MathOverflow)?;
+        let campaign_index = pool.campaign_count;
+        pool.campaign_count = campaign_index
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let campaign = &mut ctx.accounts.campaign;
+        campaign.pool = pool.key();
+        campaign.index = campaign_index;
+        campaign.sponsor = ctx.accounts.sponsor.key();
+        campaign.per_author_cap = per_author_cap;
+        campaign.total_cap = amount;
+        campaign.remaining = amount;
+        campaign.matched_total = 0;
+        campaign.ends_at = ends_at;
+        Ok(())
+    }
+
+    /// Returns whatever a campaign didn't match to its sponsor once it has
+    /// ended, and closes it.
+    pub fn close_campaign(ctx: Context<CloseCampaign>) -> Result<()> {
+        let campaign = &ctx.accounts.campaign;
+        require!(
+            Clock::get()?.unix_timestamp >= campaign.ends_at,
+            CustomError::CampaignActive
+        );
+
+        let unused = campaign.remaining;
+        let pool = &mut ctx.accounts.weekly_pool;
+        pool.reserved_matching = pool
+            .reserved_matching
+            .checked_sub(unused)
+            .ok_or(CustomError::MathOverflow)?;
+        if pool.mint.is_none() {
+            pay_from_pool(
+                &pool.to_account_info(),
+                &ctx.accounts.sponsor.to_account_info(),
+                unused,
+            )?;
+        } else {
+            pay_from_vault(
+                pool,
+                ctx.bumps.weekly_pool,
+                &ctx.accounts.mint,
+                &ctx.accounts.vault,
+                &ctx.accounts.sponsor_token_account,
+                &ctx.accounts.token_program,
+                unused,
+            )?;
+        }
+
+        msg!(
+            "Campaign {} closed: matched {}, returned {}",
+            campaign.index,
+            campaign.matched_total,
+            unused
+        );
+        Ok(())
+    }
+
+    /// Moves an entry created under the old `[title, owner]` seeds to a
+    /// post-index PDA. The new entry keeps its content, random number,
+    /// creation time and contribution, and the legacy account is closed with
+    /// its rent returned to the owner. Pool totals are not affected and the
+    /// migrated entry is not entered into any round; `weekly_pool` only picks
+    /// the pool that receives the platform cut of its tips.
+    pub fn migrate_legacy_entry(ctx: Context<MigrateEntry>, _title: String) -> Result<()> {
+        let legacy_info = ctx.accounts.legacy_entry.to_account_info();
+        let legacy = {
+            let data = legacy_info.try_borrow_data()?;
+            require!(
+                data.len() > 8 && data[..8] == *BlogEntryState::DISCRIMINATOR,
+                CustomError::InvalidLegacyEntry
+            );
+            LegacyBlogEntryState::deserialize(&mut &data[8..])?
+        };
+        require_keys_eq!(
+            legacy.owner,
+            ctx.accounts.owner.key(),
+            CustomError::Unauthorized
+        );
+
+        let profile = &mut ctx.accounts.author_profile;
+        let post_index = profile.post_count;
+        profile.post_count = post_index.checked_add(1).ok_or(CustomError::MathOverflow)?;
+
+        let blog_entry = &mut ctx.accounts.blog_entry;
+        blog_entry.owner = legacy.owner;
+        blog_entry.post_index = post_index;
+        blog_entry.title = legacy.title;
+        blog_entry.ipfs_hash = legacy.ipfs_hash;
+        blog_entry.random_number = legacy.random_number;
+        blog_entry.created_at = legacy.created_at;
+        blog_entry.tip = legacy.tip;
+        blog_entry.updated_at = legacy.created_at;
+        blog_entry.edit_count = 0;
+        blog_entry.next_comment_index = 0;
+        blog_entry.comment_count = 0;
+        blog_entry.reaction_counts = [0; REACTION_KIND_COUNT];
+        blog_entry.total_tips_received = 0;
+        blog_entry.refunded = false;
+        // legacy entries were never issued a ticket, so they can't be drawn
+        blog_entry.pool = ctx.accounts.weekly_pool.key();
+        blog_entry.round_id = NO_ROUND;
+
+        // close the legacy account by hand since it is not an Account<T>
+        let owner_info = ctx.accounts.owner.to_account_info();
+        let rent = legacy_info.lamports();
+        **owner_info.try_borrow_mut_lamports()? = owner_info
+            .lamports()
+            .checked_add(rent)
+            .ok_or(CustomError::MathOverflow)?;
+        **legacy_info.try_borrow_mut_lamports()? = 0;
+        legacy_info.assign(&System::id());
+        legacy_info.resize(0)?;
+
         Ok(())
     }
 
-    pub fn create_blog_entry(
-        ctx: Context<CreateEntry>,
-        title: String,
-        ipfs_hash: String,
-        pool_contribution: u64, // lamports user contributes to weekly pot
-    ) -> Result<()> {
-        let blog_entry = &mut ctx.accounts.blog_entry;
-        let owner = &mut ctx.accounts.owner;
-        blog_entry.owner = *owner.key;
-        blog_entry.title = title.clone();
-        blog_entry.ipfs_hash = ipfs_hash;
-        blog_entry.random_number = (Clock::get()?.unix_timestamp % 90000 + 10000) as u32; // 5-digit randomish number
-        blog_entry.created_at = Clock::get()?.unix_timestamp;
-        blog_entry.tip = pool_contribution;
+    /// Drains the pre-upgrade `[b"weekly_pool_data"]` singleton, whose 68-byte
+    /// layout no longer deserializes as a `WeeklyPool`, into a keyed SOL pool
+    /// run by the same creator. Its `total_pool` is added to the new pool's
+    /// pot, the rest of its lamports (rent) go back to the creator and the
+    /// legacy account is closed.
+    pub fn migrate_legacy_pool(ctx: Context<MigratePool>) -> Result<()> {
+        let legacy_info = ctx.accounts.legacy_pool.to_account_info();
+        let legacy = {
+            let data = legacy_info.try_borrow_data()?;
+            require!(
+                data.len() == 8 + LegacyWeeklyPool::SIZE && data[..8] == *WeeklyPool::DISCRIMINATOR,
+                CustomError::InvalidLegacyPool
+            );
+            LegacyWeeklyPool::deserialize(&mut &data[8..])?
+        };
+        require_keys_eq!(
+            legacy.creator,
+            ctx.accounts.creator.key(),
+            CustomError::NotPoolAuthority
+        );
+
+        let lamports = legacy_info.lamports();
+        let pot = legacy.total_pool.min(lamports);
+
+        let pool_info = ctx.accounts.weekly_pool.to_account_info();
+        **pool_info.try_borrow_mut_lamports()? = pool_info
+            .lamports()
+            .checked_add(pot)
+            .ok_or(CustomError::MathOverflow)?;
+        let creator_info = ctx.accounts.creator.to_account_info();
+        **creator_info.try_borrow_mut_lamports()? = creator_info
+            .lamports()
+            .checked_add(lamports.saturating_sub(pot))
+            .ok_or(CustomError::MathOverflow)?;
+        **legacy_info.try_borrow_mut_lamports()? = 0;
+        legacy_info.assign(&System::id());
+        legacy_info.resize(0)?;
+
+        let pool = &mut ctx.accounts.weekly_pool;
+        pool.total_pool = pool
+            .total_pool
+            .checked_add(pot)
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!(
+            "Migrated legacy pool: {} lamports over {} entries",
+            pot,
+            legacy.total_entries
+        );
+        Ok(())
+    }
+
+    /// Reveals the committed seed and records a PrizeClaim for every prize
+    /// tier. Winners are drawn in order (first, second, third, then
+    /// consolation), each from its own ticket derived from the seed, the
+    /// round's entropy and the slot hash chosen by lock_draw. For each
+    /// winner the remaining accounts hold the VoidTicket of every voided range
+    /// the draw runs into, then the winning entry and its uncreated PrizeClaim
+    /// address. A ticket that lands on an entry which already won, or on a
+    /// voided range, is drawn again, so no entry wins twice and no range
+    /// gains odds from its neighbours. Each VoidTicket only has to be passed
+    /// the first time the draw hits it.
+    ///
+    /// If the round is short of the config's `min_pot` or `min_entries` it is
+    /// closed without a draw instead, and the whole pot rolls over into the
+    /// next round as a jackpot; no seed reveal is needed for that.
+    pub fn declare_winner<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DeclareWinner<'info>>,
+        seed: [u8; 32],
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.weekly_pool;
+        let round = &mut ctx.accounts.current_round;
+        require!(pool.total_entries > 0, CustomError::NoEntries);
+
+        let now = Clock::get()?.unix_timestamp;
+
+        // Check if last spin was within the configured cooldown
+        let config = &ctx.accounts.pool_config;
+        if pool.last_spin_timestamp != 0
+            && now.saturating_sub(pool.last_spin_timestamp) < config.cooldown_secs
+        {
+            return Err(error!(CustomError::SpinTooEarly));
+        }
+        // past the timeout the round can only be cancelled and refunded
+        require!(
+            !draw_timed_out(pool, config, now),
+            CustomError::DrawTimedOut
+        );
+
+        if pool.total_pool < config.min_pot || pool.total_entries < config.min_entries {
+            round.rolled_over = true;
+            round.entry_count = pool.total_entries;
+
+            pool.rollover_count = pool
+                .rollover_count
+                .checked_add(1)
+                .ok_or(CustomError::MathOverflow)?;
+            pool.jackpot_carried = pool.total_pool;
+            advance_round(pool, now)?;
+            open_round(&mut ctx.accounts.next_round, pool.key(), pool.round_id, now);
+
+            msg!(
+                "Round {} rolled over with {} entries, jackpot now {}",
+                round.round_id,
+                round.entry_count,
+                pool.total_pool
+            );
+            return Ok(());
+        }
+
+        let commitment = round.draw_commitment.ok_or(CustomError::NoCommitment)?;
+        require!(
+            hash(&seed).to_bytes() == commitment,
+            CustomError::InvalidReveal
+        );
+        require!(round.reveal_slot != 0, CustomError::DrawNotLocked);
+        let slot_hash = slot_hash_at_or_after(
+            &ctx.accounts.slot_hashes.try_borrow_data()?,
+            round.reveal_slot,
+        )?;
+        // recorded on the round so the draw can be re-derived from it
+        round.entropy = hashv(&[&round.entropy, &slot_hash]).to_bytes();
+
+        // a round can't have more winners than live entries
+        let mut prizes = prize_slots(config, pool.total_pool);
+        prizes.truncate(pool.total_entries as usize);
+
+        let mut accounts = ctx.remaining_accounts.iter();
+        let mut skipped_ranges: Vec<(u64, u64)> = Vec::with_capacity(prizes.len());
+        let mut reserved = 0u64;
+        let claim_space = 8 + PrizeClaim::INIT_SPACE;
+        let claim_rent = Rent::get()?.minimum_balance(claim_space);
+        round.winners = Vec::with_capacity(prizes.len());
+
+        for (slot, prize) in prizes.iter().enumerate() {
+            let (entry_info, blog, ticket) = find_winner(
+                &mut accounts,
+                round,
+                &seed,
+                slot as u64,
+                &mut skipped_ranges,
+            )?;
+
+            // the prize stays in the pool until the winner claims it
+            let claim_info = accounts.next().ok_or(CustomError::InvalidPrizeClaim)?;
+            let entry_key = entry_info.key();
+            let (claim_key, claim_bump) =
+                Pubkey::find_program_address(&[b"prize_claim", entry_key.as_ref()], ctx.program_id);
+            require_keys_eq!(claim_info.key(), claim_key, CustomError::InvalidPrizeClaim);
+
+            create_pda_account(
+                &ctx.accounts.system_program,
+                &ctx.accounts.authority.to_account_info(),
+                claim_info,
+                claim_rent,
+                claim_space,
+                &[b"prize_claim", entry_key.as_ref(), &[claim_bump]],
+            )?;
+            let claim = PrizeClaim {
+                pool: round.pool,
+                round_id: round.round_id,
+                entry: entry_key,
+                winner: blog.owner,
+                payer: ctx.accounts.authority.key(),
+                prize: *prize,
+                expires_at: now
+                    .checked_add(config.claim_window_secs)
+                    .ok_or(CustomError::MathOverflow)?,
+            };
+            claim.try_serialize(&mut &mut claim_info.try_borrow_mut_data()?[..])?;
+
+            if slot == 0 {
+                pool.last_winner_number = blog.random_number;
+            }
+            msg!(
+                "Winner #{} blog: {:?}, random_number: {}, prize: {}",
+                slot + 1,
+                blog.title,
+                blog.random_number,
+                prize
+            );
+
+            let ticket_end = blog
+                .ticket_start
+                .checked_add(blog.tickets)
+                .ok_or(CustomError::MathOverflow)?;
+            skipped_ranges.push((blog.ticket_start, ticket_end));
+            round.winners.push(RoundWinner {
+                entry: entry_key,
+                owner: blog.owner,
+                ticket,
+                prize: *prize,
+            });
+            reserved = reserved
+                .checked_add(*prize)
+                .ok_or(CustomError::MathOverflow)?;
+        }
+
+        // whatever is left after prizes and the fee stays in the pool for next round
+        let owner_share = apply_bps(pool.total_pool, config.creator_fee_bps);
+        let carried_over = pool
+            .total_pool
+            .checked_sub(reserved)
+            .and_then(|rest| rest.checked_sub(owner_share))
+            .ok_or(CustomError::MathOverflow)?;
+
+        // the creator fee accrues in the treasury until withdraw_fees; token
+        // pools keep it in the vault and only record it there
+        if pool.mint.is_none() {
+            pay_from_pool(
+                &pool.to_account_info(),
+                &ctx.accounts.treasury.to_account_info(),
+                owner_share,
+            )?;
+        }
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.accrued = treasury
+            .accrued
+            .checked_add(owner_share)
+            .ok_or(CustomError::MathOverflow)?;
+
+        // keep what's needed to re-derive this draw on the round itself
+        round.seed = seed;
+        round.entry_count = pool.total_entries;
+        round.drawn_at = now;
+
+        pool.total_pool = carried_over;
+        pool.reserved_prizes = pool
+            .reserved_prizes
+            .checked_add(reserved)
+            .ok_or(CustomError::MathOverflow)?;
+        pool.rollover_count = 0;
+        pool.jackpot_carried = 0;
+        advance_round(pool, now)?;
+
+        open_round(&mut ctx.accounts.next_round, pool.key(), pool.round_id, now);
+
+        Ok(())
+    }
+
+    /// Cancels the open round without a draw and opens the next one. The
+    /// pool authority can do this until the locked reveal slot is reached,
+    /// since past it the outcome is known. Anyone can once the reveal slot's
+    /// hash has left SlotHashes, or once the round has gone
+    /// `draw_timeout_secs` past its cooldown without being drawn. The round's
+    /// contributions are set aside for refund_entry and anything else in the
+    /// pot carries over.
+    pub fn cancel_round(ctx: Context<CancelRound>) -> Result<()> {
+        let pool = &mut ctx.accounts.weekly_pool;
+        let round = &mut ctx.accounts.current_round;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let revealed = round.reveal_slot != 0 && clock.slot >= round.reveal_slot;
+        let reveal_expired = round.reveal_slot != 0
+            && clock.slot >= round.reveal_slot.saturating_add(SLOT_HASHES_DEPTH);
+        require!(
+            (is_draw_authority(pool, &ctx.accounts.caller.key()) && !revealed)
+                || reveal_expired
+                || draw_timed_out(pool, &ctx.accounts.pool_config, now),
+            CustomError::CancelNotAllowed
+        );
+
+        round.cancelled = true;
+        round.entry_count = pool.total_entries;
+
+        let refundable = pool.round_contributions;
+        pool.total_pool = pool
+            .total_pool
+            .checked_sub(refundable)
+            .ok_or(CustomError::MathOverflow)?;
+        pool.reserved_refunds = pool
+            .reserved_refunds
+            .checked_add(refundable)
+            .ok_or(CustomError::MathOverflow)?;
+        advance_round(pool, now)?;
+
+        open_round(&mut ctx.accounts.next_round, pool.key(), pool.round_id, now);
+
+        msg!(
+            "Round {} cancelled, {} set aside for refunds",
+            round.round_id,
+            refundable
+        );
+        Ok(())
+    }
+
+    /// Returns an entry's `pool_contribution` to its owner after its round
+    /// was cancelled. Each entry can be refunded once.
+    pub fn refund_entry(ctx: Context<RefundEntry>) -> Result<()> {
+        require!(ctx.accounts.round.cancelled, CustomError::RoundNotCancelled);
+        let blog_entry = &mut ctx.accounts.blog_entry;
+        require!(!blog_entry.refunded, CustomError::AlreadyRefunded);
+        blog_entry.refunded = true;
+        let amount = blog_entry.tip;
+
+        let pool = &mut ctx.accounts.weekly_pool;
+        pool.reserved_refunds = pool
+            .reserved_refunds
+            .checked_sub(amount)
+            .ok_or(CustomError::MathOverflow)?;
+        if pool.mint.is_none() {
+            pay_from_pool(
+                &pool.to_account_info(),
+                &ctx.accounts.owner.to_account_info(),
+                amount,
+            )?;
+        } else {
+            pay_from_vault(
+                pool,
+                ctx.bumps.weekly_pool,
+                &ctx.accounts.mint,
+                &ctx.accounts.vault,
+                &ctx.accounts.owner_token_account,
+                &ctx.accounts.token_program,
+                amount,
+            )?;
+        }
+
+        msg!("Refunded {} for blog: {:?}", amount, blog_entry.title);
+        Ok(())
+    }
+
+    /// Pays a recorded prize to its winner. The claim account's rent goes
+    /// back to whoever ran the draw.
+    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
+        let claim = &ctx.accounts.prize_claim;
+        let now = Clock::get()?.unix_timestamp;
+        require!(now < claim.expires_at, CustomError::ClaimExpired);
+
+        let pool = &mut ctx.accounts.weekly_pool;
+        pool.reserved_prizes = pool
+            .reserved_prizes
+            .checked_sub(claim.prize)
+            .ok_or(CustomError::MathOverflow)?;
+        if pool.mint.is_none() {
+            pay_from_pool(
+                &pool.to_account_info(),
+                &ctx.accounts.winner.to_account_info(),
+                claim.prize,
+            )?;
+        } else {
+            pay_from_vault(
+                pool,
+                ctx.bumps.weekly_pool,
+                &ctx.accounts.mint,
+                &ctx.accounts.vault,
+                &ctx.accounts.winner_token_account,
+                &ctx.accounts.token_program,
+                claim.prize,
+            )?;
+        }
+
+        let profile = &mut ctx.accounts.winner_profile;
+        profile.total_winnings = profile
+            .total_winnings
+            .checked_add(claim.prize)
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!(
+            "Prize of {} claimed for round {}",
+            claim.prize,
+            claim.round_id
+        );
+        Ok(())
+    }
+
+    /// Rolls a prize nobody claimed within the claim window back into the
+    /// open round's pot. Anyone can call it once the claim has expired.
+    pub fn expire_prize(ctx: Context<ExpirePrize>) -> Result<()> {
+        let claim = &ctx.accounts.prize_claim;
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= claim.expires_at, CustomError::ClaimNotExpired);
+
+        let pool = &mut ctx.accounts.weekly_pool;
+        pool.reserved_prizes = pool
+            .reserved_prizes
+            .checked_sub(claim.prize)
+            .ok_or(CustomError::MathOverflow)?;
+        pool.total_pool = pool
+            .total_pool
+            .checked_add(claim.prize)
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!(
+            "Unclaimed prize of {} from round {} rolled over",
+            claim.prize,
+            claim.round_id
+        );
+        Ok(())
+    }
+
+    /// Syncs total_pool with what the pool account actually holds above its
+    /// rent-exempt minimum, reserved prizes and refunds, and matching escrow,
+    /// so lamports sent to the pool directly end up in the open round's pot.
+    /// Token pools are synced against the vault balance instead.
+    pub fn reconcile_pool(ctx: Context<ReconcilePool>) -> Result<()> {
+        let pool = &mut ctx.accounts.weekly_pool;
+        let held = if pool.mint.is_none() {
+            let pool_info = pool.to_account_info();
+            let rent_floor = Rent::get()?.minimum_balance(pool_info.data_len());
+            pool_info
+                .lamports()
+                .checked_sub(rent_floor)
+                .ok_or(CustomError::MathOverflow)?
+        } else {
+            let vault = ctx
+                .accounts
+                .vault
+                .as_ref()
+                .ok_or(CustomError::TokenAccountsRequired)?;
+            // unwithdrawn fees of a token pool sit in the same vault
+            vault
+                .amount
+                .checked_sub(ctx.accounts.treasury.accrued)
+                .ok_or(CustomError::MathOverflow)?
+        };
+        let available = held
+            .checked_sub(pool.reserved_prizes)
+            .and_then(|rest| rest.checked_sub(pool.reserved_refunds))
+            .and_then(|rest| rest.checked_sub(pool.reserved_matching))
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!(
+            "Pool reconciled: total_pool {} -> {}",
+            pool.total_pool,
+            available
+        );
+        pool.total_pool = available;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreateProfile<'info> {
+    #[account(
+        init,
+        seeds = [b"author", owner.key().as_ref()],
+        bump,
+        space = 8 + AuthorProfile::INIT_SPACE,
+        payer = owner
+    )]
+    pub author_profile: Account<'info, AuthorProfile>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProfile<'info> {
+    #[account(
+        mut,
+        seeds = [b"author", owner.key().as_ref()],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub author_profile: Account<'info, AuthorProfile>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(handle: String)]
+pub struct ClaimHandle<'info> {
+    #[account(
+        init,
+        seeds = [b"handle", handle.as_bytes()],
+        bump,
+        space = 8 + HandleRegistry::INIT_SPACE,
+        payer = owner
+    )]
+    pub handle_registry: Account<'info, HandleRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"author", owner.key().as_ref()],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub author_profile: Account<'info, AuthorProfile>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(handle: String)]
+pub struct ReleaseHandle<'info> {
+    #[account(
+        mut,
+        seeds = [b"handle", handle.as_bytes()],
+        bump,
+        has_one = owner @ CustomError::Unauthorized,
+        close = owner
+    )]
+    pub handle_registry: Account<'info, HandleRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"author", owner.key().as_ref()],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub author_profile: Account<'info, AuthorProfile>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(handle: String)]
+pub struct TransferHandle<'info> {
+    #[account(
+        mut,
+        seeds = [b"handle", handle.as_bytes()],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub handle_registry: Account<'info, HandleRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"author", owner.key().as_ref()],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub author_profile: Account<'info, AuthorProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"author", new_owner.key().as_ref()],
+        bump,
+        constraint = new_owner_profile.owner != owner.key() @ CustomError::HandleAlreadySet
+    )]
+    pub new_owner_profile: Account<'info, AuthorProfile>,
+
+    pub owner: Signer<'info>,
+
+    // the receiving wallet co-signs so a handle can't be pushed onto it
+    pub new_owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateEntry<'info> {
+    #[account(
+        mut,
+        seeds = [b"author", owner.key().as_ref()],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub author_profile: Account<'info, AuthorProfile>,
+
+    #[account(
+        init,
+        seeds = [
+            b"post",
+            owner.key().as_ref(),
+            &author_profile.post_count.to_le_bytes()
+        ],
+        bump,
+        space = 8 + BlogEntryState::INIT_SPACE,
+        payer = owner
+    )]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(seeds = [b"pool_config", weekly_pool.key().as_ref()], bump)]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub round: Account<'info, RoundState>,
+
+    // token pools only: the pool's mint, the author's token account and the vault
+    #[account(constraint = Some(mint.key()) == weekly_pool.mint @ CustomError::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub owner_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [b"vault", weekly_pool.key().as_ref()], bump)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    // optional matching campaign for this pool, with the author's running total
+    #[account(
+        mut,
+        constraint = campaign.pool == weekly_pool.key() @ CustomError::PoolMismatch
+    )]
+    pub campaign: Option<Account<'info, MatchingCampaign>>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"campaign_match",
+            campaign.as_ref().map(|c| c.key()).unwrap_or_default().as_ref(),
+            owner.key().as_ref()
+        ],
+        bump,
+        space = 8 + CampaignMatch::INIT_SPACE,
+        payer = owner
+    )]
+    pub campaign_match: Option<Account<'info, CampaignMatch>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEntry<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"post",
+            owner.key().as_ref(),
+            &blog_entry.post_index.to_le_bytes()
+        ],
+        bump,
+        has_one = owner @ CustomError::Unauthorized,
+        realloc = 8 + BlogEntryState::INIT_SPACE,
+        realloc::payer = owner,
+        realloc::zero = true
+    )]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(
+        init,
+        seeds = [
+            b"revision",
+            blog_entry.key().as_ref(),
+            &blog_entry.edit_count.to_le_bytes()
+        ],
+        bump,
+        space = 8 + BlogRevision::INIT_SPACE,
+        payer = owner
+    )]
+    pub revision: Account<'info, BlogRevision>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tag: String)]
+pub struct CreateTag<'info> {
+    #[account(
+        init,
+        seeds = [b"tag", tag.as_bytes()],
+        bump,
+        space = 8 + TagState::INIT_SPACE,
+        payer = payer
+    )]
+    pub tag_state: Account<'info, TagState>,
+
+    #[account(
+        init,
+        seeds = [b"tag_page", tag.as_bytes(), &0u32.to_le_bytes()],
+        bump,
+        space = 8 + TagIndexPage::INIT_SPACE,
+        payer = payer
+    )]
+    pub first_page: Account<'info, TagIndexPage>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tag: String)]
+pub struct AddTagPage<'info> {
+    #[account(mut, seeds = [b"tag", tag.as_bytes()], bump)]
+    pub tag_state: Account<'info, TagState>,
+
+    #[account(
+        seeds = [b"tag_page", tag.as_bytes(), &tag_state.current_page.to_le_bytes()],
+        bump
+    )]
+    pub current_page: Account<'info, TagIndexPage>,
+
+    #[account(
+        init,
+        seeds = [
+            b"tag_page",
+            tag.as_bytes(),
+            &tag_state.current_page.saturating_add(1).to_le_bytes()
+        ],
+        bump,
+        space = 8 + TagIndexPage::INIT_SPACE,
+        payer = payer
+    )]
+    pub new_page: Account<'info, TagIndexPage>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tag: String)]
+pub struct TagEntry<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"post",
+            owner.key().as_ref(),
+            &blog_entry.post_index.to_le_bytes()
+        ],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(mut, seeds = [b"tag", tag.as_bytes()], bump)]
+    pub tag_state: Account<'info, TagState>,
+
+    #[account(
+        mut,
+        seeds = [b"tag_page", tag.as_bytes(), &tag_state.current_page.to_le_bytes()],
+        bump
+    )]
+    pub tag_page: Account<'info, TagIndexPage>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tag: String, page_index: u32)]
+pub struct UntagEntry<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"post",
+            owner.key().as_ref(),
+            &blog_entry.post_index.to_le_bytes()
+        ],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(mut, seeds = [b"tag", tag.as_bytes()], bump)]
+    pub tag_state: Account<'info, TagState>,
+
+    #[account(
+        mut,
+        seeds = [b"tag_page", tag.as_bytes(), &page_index.to_le_bytes()],
+        bump
+    )]
+    pub tag_page: Account<'info, TagIndexPage>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PostComment<'info> {
+    #[account(mut)]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(
+        init,
+        seeds = [
+            b"comment",
+            blog_entry.key().as_ref(),
+            &blog_entry.next_comment_index.to_le_bytes()
+        ],
+        bump,
+        space = 8 + CommentState::INIT_SPACE,
+        payer = author
+    )]
+    pub comment: Account<'info, CommentState>,
+
+    #[account(mut)]
+    pub parent_comment: Option<Account<'info, CommentState>>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EditComment<'info> {
+    #[account(
+        mut,
+        seeds = [b"comment", comment.entry.as_ref(), &comment.index.to_le_bytes()],
+        bump,
+        has_one = author @ CustomError::Unauthorized
+    )]
+    pub comment: Account<'info, CommentState>,
+
+    pub author: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteComment<'info> {
+    /// CHECK: the commented entry, which may have been deleted since
+    #[account(mut, address = comment.entry)]
+    pub blog_entry: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"comment", comment.entry.as_ref(), &comment.index.to_le_bytes()],
+        bump,
+        has_one = author @ CustomError::Unauthorized,
+        close = author
+    )]
+    pub comment: Account<'info, CommentState>,
+
+    /// CHECK: required when deleting a reply so its parent's reply_count
+    /// stays right; the handler checks it is the parent's PDA
+    #[account(mut)]
+    pub parent_comment: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct React<'info> {
+    #[account(mut)]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(
+        init,
+        seeds = [b"reaction", blog_entry.key().as_ref(), reactor.key().as_ref()],
+        bump,
+        space = 8 + Reaction::INIT_SPACE,
+        payer = reactor
+    )]
+    pub reaction: Account<'info, Reaction>,
+
+    #[account(mut)]
+    pub reactor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Unreact<'info> {
+    /// CHECK: the reacted entry, which may have been deleted since
+    #[account(mut, address = reaction.entry)]
+    pub blog_entry: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"reaction", reaction.entry.as_ref(), reactor.key().as_ref()],
+        bump,
+        has_one = reactor @ CustomError::Unauthorized,
+        close = reactor
+    )]
+    pub reaction: Account<'info, Reaction>,
+
+    #[account(mut)]
+    pub reactor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TipAuthor<'info> {
+    #[account(mut)]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    /// CHECK: receives lamports only; must be the entry owner
+    #[account(mut, address = blog_entry.owner @ CustomError::AuthorMismatch)]
+    pub author: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        address = blog_entry.pool @ CustomError::PoolMismatch
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(seeds = [b"pool_config", weekly_pool.key().as_ref()], bump)]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    // tippers without a profile can still tip, they just aren't tracked
+    #[account(mut, seeds = [b"author", tipper.key().as_ref()], bump)]
+    pub tipper_profile: Option<Account<'info, AuthorProfile>>,
+
+    #[account(mut)]
+    pub tipper: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SponsorPool<'info> {
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(
+        init,
+        seeds = [
+            b"sponsorship",
+            weekly_pool.key().as_ref(),
+            &weekly_pool.sponsorship_count.to_le_bytes()
+        ],
+        bump,
+        space = 8 + Sponsorship::INIT_SPACE,
+        payer = sponsor
+    )]
+    pub sponsorship: Account<'info, Sponsorship>,
+
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    // token pools only: the pool's mint, the sponsor's token account and the vault
+    #[account(constraint = Some(mint.key()) == weekly_pool.mint @ CustomError::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub sponsor_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [b"vault", weekly_pool.key().as_ref()], bump)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateCampaign<'info> {
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(
+        init,
+        seeds = [
+            b"campaign",
+            weekly_pool.key().as_ref(),
+            &weekly_pool.campaign_count.to_le_bytes()
+        ],
+        bump,
+        space = 8 + MatchingCampaign::INIT_SPACE,
+        payer = sponsor
+    )]
+    pub campaign: Account<'info, MatchingCampaign>,
+
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    // token pools only: the pool's mint, the sponsor's token account and the vault
+    #[account(constraint = Some(mint.key()) == weekly_pool.mint @ CustomError::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub sponsor_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [b"vault", weekly_pool.key().as_ref()], bump)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseCampaign<'info> {
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        address = campaign.pool @ CustomError::PoolMismatch
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"campaign",
+            weekly_pool.key().as_ref(),
+            &campaign.index.to_le_bytes()
+        ],
+        bump,
+        has_one = sponsor @ CustomError::Unauthorized,
+        close = sponsor
+    )]
+    pub campaign: Account<'info, MatchingCampaign>,
+
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    // token pools only: unused funds are returned from the vault
+    #[account(constraint = Some(mint.key()) == weekly_pool.mint @ CustomError::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, seeds = [b"vault", weekly_pool.key().as_ref()], bump)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = sponsor_token_account.owner == sponsor.key() @ CustomError::Unauthorized
+    )]
+    pub sponsor_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteEntry<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"post",
+            owner.key().as_ref(),
+            &blog_entry.post_index.to_le_bytes()
+        ],
+        bump,
+        has_one = owner @ CustomError::Unauthorized,
+        close = owner
+    )]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        address = blog_entry.pool @ CustomError::PoolMismatch
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(seeds = [b"pool_config", weekly_pool.key().as_ref()], bump)]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    /// CHECK: the round the entry was entered into, read by the handler if it
+    /// exists
+    #[account(
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &blog_entry.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub round: UncheckedAccount<'info>,
+
+    // only needed when the entry is in the open round
+    #[account(
+        init,
+        seeds = [
+            b"void_ticket".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.to_le_bytes(),
+            &blog_entry.ticket_start.to_le_bytes()
+        ],
+        bump,
+        space = 8 + VoidTicket::INIT_SPACE,
+        payer = owner
+    )]
+    pub void_ticket: Option<Account<'info, VoidTicket>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(title: String)]
+pub struct MigrateEntry<'info> {
+    /// CHECK: title-seeded entry in the legacy layout; seeds and program
+    /// ownership are checked here and the data is decoded by the handler
+    #[account(
+        mut,
+        seeds = [title.as_bytes(), owner.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub legacy_entry: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"author", owner.key().as_ref()],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub author_profile: Account<'info, AuthorProfile>,
+
+    #[account(
+        init,
+        seeds = [
+            b"post",
+            owner.key().as_ref(),
+            &author_profile.post_count.to_le_bytes()
+        ],
+        bump,
+        space = 8 + BlogEntryState::INIT_SPACE,
+        payer = owner
+    )]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    /// CHECK: the pre-upgrade singleton pool; seeds and program ownership are
+    /// checked here and the data is decoded by the handler
+    #[account(mut, seeds = [b"weekly_pool_data"], bump, owner = crate::ID)]
+    pub legacy_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        has_one = creator @ CustomError::NotPoolAuthority,
+        constraint = weekly_pool.mint.is_none() @ CustomError::InvalidMint
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CommitDraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        constraint = is_draw_authority(&weekly_pool, &authority.key()) @ CustomError::NotPoolAuthority
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(seeds = [b"pool_config", weekly_pool.key().as_ref()], bump)]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub round: Account<'info, RoundState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LockDraw<'info> {
+    #[account(
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        constraint = is_draw_authority(&weekly_pool, &authority.key()) @ CustomError::NotPoolAuthority
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(seeds = [b"pool_config", weekly_pool.key().as_ref()], bump)]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub round: Account<'info, RoundState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeclareWinner<'info> {
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        constraint = is_draw_authority(&weekly_pool, &authority.key()) @ CustomError::NotPoolAuthority
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(seeds = [b"pool_config", weekly_pool.key().as_ref()], bump)]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub current_round: Account<'info, RoundState>,
+
+    #[account(
+        init,
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.saturating_add(1).to_le_bytes()
+        ],
+        bump,
+        space = 8 + RoundState::INIT_SPACE,
+        payer = authority
+    )]
+    pub next_round: Account<'info, RoundState>,
+
+    #[account(mut, seeds = [b"treasury", weekly_pool.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    /// CHECK: the SlotHashes sysvar, read by hand since it is too large to
+    /// deserialize
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    // the creator or a registered crank; pays rent for the next round and prize claims
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPrize<'info> {
+    #[account(
+        mut,
+        seeds = [b"prize_claim", prize_claim.entry.as_ref()],
+        bump,
+        has_one = winner @ CustomError::WinnerMismatch,
+        has_one = payer @ CustomError::InvalidPrizeClaim,
+        close = payer
+    )]
+    pub prize_claim: Account<'info, PrizeClaim>,
+
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        address = prize_claim.pool @ CustomError::PoolMismatch
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(
+        mut,
+        seeds = [b"author", winner.key().as_ref()],
+        bump
+    )]
+    pub winner_profile: Account<'info, AuthorProfile>,
+
+    #[account(mut)]
+    pub winner: Signer<'info>,
+
+    /// CHECK: paid the claim's rent at draw time; gets it back on close
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    // token pools only: the prize is paid from the vault
+    #[account(constraint = Some(mint.key()) == weekly_pool.mint @ CustomError::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, seeds = [b"vault", weekly_pool.key().as_ref()], bump)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = winner_token_account.owner == winner.key() @ CustomError::WinnerMismatch
+    )]
+    pub winner_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ExpirePrize<'info> {
+    #[account(
+        mut,
+        seeds = [b"prize_claim", prize_claim.entry.as_ref()],
+        bump,
+        has_one = payer @ CustomError::InvalidPrizeClaim,
+        close = payer
+    )]
+    pub prize_claim: Account<'info, PrizeClaim>,
+
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        address = prize_claim.pool @ CustomError::PoolMismatch
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    /// CHECK: paid the claim's rent at draw time; gets it back on close
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRound<'info> {
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(seeds = [b"pool_config", weekly_pool.key().as_ref()], bump)]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub current_round: Account<'info, RoundState>,
+
+    #[account(
+        init,
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.saturating_add(1).to_le_bytes()
+        ],
+        bump,
+        space = 8 + RoundState::INIT_SPACE,
+        payer = caller
+    )]
+    pub next_round: Account<'info, RoundState>,
+
+    // the pool authority before the reveal slot, or anyone once the reveal
+    // has expired or the draw has timed out
+    #[account(mut)]
+    pub caller: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefundEntry<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"post",
+            owner.key().as_ref(),
+            &blog_entry.post_index.to_le_bytes()
+        ],
+        bump,
+        has_one = owner @ CustomError::Unauthorized
+    )]
+    pub blog_entry: Account<'info, BlogEntryState>,
+
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        address = blog_entry.pool @ CustomError::PoolMismatch
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &blog_entry.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub round: Account<'info, RoundState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // token pools only: the refund is paid from the vault
+    #[account(constraint = Some(mint.key()) == weekly_pool.mint @ CustomError::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, seeds = [b"vault", weekly_pool.key().as_ref()], bump)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == owner.key() @ CustomError::Unauthorized
+    )]
+    pub owner_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct SetBeneficiaries<'info> {
+    #[account(
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        has_one = creator @ CustomError::NotPoolAuthority
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(mut, seeds = [b"treasury", weekly_pool.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        has_one = creator @ CustomError::NotPoolAuthority
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(mut, seeds = [b"treasury", weekly_pool.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    pub creator: Signer<'info>,
+
+    // token pools only: fees are paid from the vault
+    #[account(constraint = Some(mint.key()) == weekly_pool.mint @ CustomError::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, seeds = [b"vault", weekly_pool.key().as_ref()], bump)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ReconcilePool<'info> {
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        constraint = is_draw_authority(&weekly_pool, &authority.key()) @ CustomError::NotPoolAuthority
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    pub authority: Signer<'info>,
+
+    #[account(seeds = [b"treasury", weekly_pool.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    // token pools only
+    #[account(seeds = [b"vault", weekly_pool.key().as_ref()], bump)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct ManageCranks<'info> {
+    #[account(
+        mut,
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        has_one = creator @ CustomError::NotPoolAuthority
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        seeds = [b"weekly_pool_data".as_ref(), &weekly_pool.pool_id.to_le_bytes()],
+        bump,
+        has_one = creator @ CustomError::NotPoolAuthority
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(mut, seeds = [b"pool_config", weekly_pool.key().as_ref()], bump)]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    #[account(
+        seeds = [
+            b"round".as_ref(),
+            weekly_pool.key().as_ref(),
+            &weekly_pool.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub round: Account<'info, RoundState>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct InitializePool<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + WeeklyPool::INIT_SPACE,
+        seeds = [b"weekly_pool_data".as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + PoolConfig::INIT_SPACE,
+        seeds = [b"pool_config", weekly_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + RoundState::INIT_SPACE,
+        seeds = [b"round".as_ref(), weekly_pool.key().as_ref(), &0u64.to_le_bytes()],
+        bump
+    )]
+    pub first_round: Account<'info, RoundState>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [b"treasury", weekly_pool.key().as_ref()],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct InitializeTokenPool<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + WeeklyPool::INIT_SPACE,
+        seeds = [b"weekly_pool_data".as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub weekly_pool: Account<'info, WeeklyPool>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + PoolConfig::INIT_SPACE,
+        seeds = [b"pool_config", weekly_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_config: Account<'info, PoolConfig>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + RoundState::INIT_SPACE,
+        seeds = [b"round".as_ref(), weekly_pool.key().as_ref(), &0u64.to_le_bytes()],
+        bump
+    )]
+    pub first_round: Account<'info, RoundState>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [b"treasury", weekly_pool.key().as_ref()],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"vault", weekly_pool.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = weekly_pool,
+        token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct BlogEntryState {
+    pub owner: Pubkey,
+    pub post_index: u64,
+    #[max_len(50)]
+    pub title: String,
+    #[max_len(100)]
+    pub ipfs_hash: String,
+    pub random_number: u32,
+    pub created_at: i64,
+    pub tip: u64,
+    pub updated_at: i64,
+    pub edit_count: u32,
+    #[max_len(5, 32)]
+    pub tags: Vec<String>,
+    pub next_comment_index: u64, // seed index for the next CommentState
+    pub comment_count: u64,      // comments currently open on this entry
+    pub reaction_counts: [u64; REACTION_KIND_COUNT], // indexed by ReactionKind
+    pub total_tips_received: u64, // direct tips, net of the platform cut
+    pub pool: Pubkey,            // WeeklyPool the entry was entered into
+    pub round_id: u64,           // round the entry was entered into
+    pub ticket_start: u64,       // first ticket of the entry's range in that round
+    pub tickets: u64,            // size of the range, see tickets_for
+    pub refunded: bool,          // contribution returned after a cancelled round
+}
+
+// Layout of entries created before entries were seeded by post index.
+// Only used to decode them in migrate_legacy_entry.
+#[derive(AnchorDeserialize)]
+pub struct LegacyBlogEntryState {
+    pub owner: Pubkey,
+    pub title: String,
+    pub ipfs_hash: String,
+    pub random_number: u32,
+    pub created_at: i64,
+    pub tip: u64,
+}
+
+// Layout of the singleton pool from before pools were keyed by pool_id.
+// Only used to decode it in migrate_legacy_pool.
+#[derive(AnchorDeserialize)]
+pub struct LegacyWeeklyPool {
+    pub creator: Pubkey,
+    pub total_pool: u64,
+    pub total_entries: u64,
+    pub last_winner_number: u32,
+    pub last_spin_timestamp: i64,
+}
+
+impl LegacyWeeklyPool {
+    const SIZE: usize = 32 + 8 + 8 + 4 + 8;
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AuthorProfile {
+    pub owner: Pubkey,
+    #[max_len(32)]
+    pub handle: String,
+    #[max_len(160)]
+    pub bio: String,
+    #[max_len(100)]
+    pub avatar_cid: String,
+    pub post_count: u64,      // posts ever created, also the next post_index
+    pub total_tips_sent: u64, // lamports tipped to other authors
+    pub total_winnings: u64,
+    pub total_contributed: u64, // pool contributions across all entries
+}
+
+// Maps a normalized handle (lowercase a-z, 0-9 and '_', no leading '@')
+// to the wallet that owns it. Seeded by the handle bytes, so each handle
+// can exist at most once.
+#[account]
+#[derive(InitSpace)]
+pub struct HandleRegistry {
+    pub owner: Pubkey,
+    #[max_len(32)]
+    pub handle: String,
+    pub claimed_at: i64,
+}
+
+// Pages for a tag live at indices 0..=current_page; new entries are always
+// appended to current_page.
+#[account]
+#[derive(InitSpace)]
+pub struct TagState {
+    #[max_len(32)]
+    pub tag: String,
+    pub current_page: u32,
+    pub entry_count: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct TagIndexPage {
+    #[max_len(32)]
+    pub tag: String,
+    pub page_index: u32,
+    #[max_len(32)]
+    pub entries: Vec<Pubkey>,
+}
+
+// Either body or body_cid (or both) is set. parent is the index of the
+// comment being replied to on the same entry, None for top-level comments.
+#[account]
+#[derive(InitSpace)]
+pub struct CommentState {
+    pub entry: Pubkey,
+    pub author: Pubkey,
+    pub index: u64,
+    pub parent: Option<u64>,
+    #[max_len(280)]
+    pub body: String,
+    #[max_len(100)]
+    pub body_cid: String,
+    pub created_at: i64,
+    pub edited_at: i64,
+    pub reply_count: u32,
+}
+
+// One per (entry, wallet); the PDA seeds are what enforce a single vote.
+#[account]
+#[derive(InitSpace)]
+pub struct Reaction {
+    pub entry: Pubkey,
+    pub reactor: Pubkey,
+    pub kind: ReactionKind,
+    pub created_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum ReactionKind {
+    Like,
+    Love,
+    Insightful,
+    Funny,
+    Celebrate,
+}
+
+// Marks the ticket range [ticket_start, ticket_end) of an entry deleted
+// during its round, so the draw can prove the range has no owner and move
+// past it.
+#[account]
+#[derive(InitSpace)]
+pub struct VoidTicket {
+    pub pool: Pubkey,
+    pub round_id: u64,
+    pub ticket_start: u64,
+    pub ticket_end: u64,
+}
+
+// One per sponsor_pool deposit, seeded by pool and index. Kept as the public
+// record of who funded which round.
+#[account]
+#[derive(InitSpace)]
+pub struct Sponsorship {
+    pub pool: Pubkey,
+    pub index: u64,
+    pub round_id: u64, // round that was open when the deposit landed
+    pub sponsor: Pubkey,
+    pub amount: u64,
+    #[max_len(64)]
+    pub name: String,
+    #[max_len(100)]
+    pub cid: String, // IPFS CID of the sponsor's logo or page, may be empty
+    pub created_at: i64,
+}
+
+// Collects a pool's creator fees between withdrawals, seeded by pool. SOL
+// fees are held in this account's lamports; a token pool's stay in its vault
+// and are only counted here.
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    pub pool: Pubkey,
+    pub accrued: u64,
+    pub total_withdrawn: u64,
+    #[max_len(5)]
+    pub beneficiaries: Vec<Beneficiary>, // empty means everything goes to the creator
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct Beneficiary {
+    pub wallet: Pubkey,
+    pub bps: u16,
+}
+
+// A sponsor's matching fund, escrowed in the pool. total_cap is what was
+// deposited; remaining is what can still be matched until ends_at.
+#[account]
+#[derive(InitSpace)]
+pub struct MatchingCampaign {
+    pub pool: Pubkey,
+    pub index: u64,
+    pub sponsor: Pubkey,
+    pub per_author_cap: u64,
+    pub total_cap: u64,
+    pub remaining: u64,
+    pub matched_total: u64,
+    pub ends_at: i64,
+}
+
+// How much of a campaign one author has been matched so far. Seeded by
+// campaign and author, created on the author's first matched entry.
+#[account]
+#[derive(InitSpace)]
+pub struct CampaignMatch {
+    pub campaign: Pubkey,
+    pub author: Pubkey,
+    pub matched: u64,
+}
+
+// Created by declare_winner for each winning entry and closed by claim_prize,
+// or by expire_prize once expires_at has passed.
+#[account]
+#[derive(InitSpace)]
+pub struct PrizeClaim {
+    pub pool: Pubkey,
+    pub round_id: u64,
+    pub entry: Pubkey,
+    pub winner: Pubkey,
+    pub payer: Pubkey, // ran the draw and paid this account's rent
+    pub prize: u64,
+    pub expires_at: i64,
+}
+
+// Written once by update_blog_entry and never modified or closed afterwards.
+// Revisions for an entry live at indices 0..edit_count.
+#[account]
+#[derive(InitSpace)]
+pub struct BlogRevision {
+    pub entry: Pubkey,
+    pub index: u32,
+    #[max_len(50)]
+    pub title: String,
+    #[max_len(100)]
+    pub ipfs_hash: String,
+    pub editor: Pubkey,
+    pub timestamp: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct WeeklyPool {
+    pub pool_id: u64, // seed; any number of pools can run side by side
+    pub creator: Pubkey,
+    pub mint: Option<Pubkey>, // None for SOL; token pools keep their pot in the vault PDA
+    pub total_pool: u64,
+    pub total_entries: u64,
+    pub last_winner_number: u32,
+    pub last_spin_timestamp: i64,
+    pub round_id: u64, // the open round; earlier RoundState accounts are history
+    #[max_len(5)]
+    pub cranks: Vec<Pubkey>, // keys besides the creator allowed to run draws
+    pub reserved_prizes: u64, // held for unredeemed PrizeClaims, not part of total_pool
+    pub round_contributions: u64, // open round's contributions still refundable if cancelled
+    pub reserved_refunds: u64, // owed to entries of cancelled rounds, not part of total_pool
+    pub rollover_count: u32, // rounds rolled over in a row since the last draw
+    pub jackpot_carried: u64, // pot carried into the open round by the last rollover
+    pub sponsorship_count: u64, // seed index for the next Sponsorship
+    pub campaign_count: u64, // seed index for the next MatchingCampaign
+    pub reserved_matching: u64, // unmatched campaign escrow, not part of total_pool
+}
 
-        // transfer SOL to weekly pool
-        let lamports = pool_contribution;
+// Pool parameters the creator can change without a redeploy. Shares are in
+// basis points of the pot; anything not paid to winners or the creator
+// carries over to the next round. A zero tier is skipped, and the
+// consolation share is split equally between consolation_winners.
+#[account]
+#[derive(InitSpace)]
+pub struct PoolConfig {
+    pub cooldown_secs: i64,
+    pub claim_window_secs: i64, // how long winners have to claim before rollover
+    pub draw_timeout_secs: i64, // grace after the cooldown before an undrawn round can be cancelled
+    pub tier_shares_bps: [u16; PRIZE_TIERS], // first, second, third prize
+    pub consolation_share_bps: u16,
+    pub consolation_winners: u8,
+    pub creator_fee_bps: u16,
+    pub tip_fee_bps: u16, // share of each tip_author payment routed to the pool
+    pub min_contribution: u64,
+    pub max_contribution: u64,
+    pub ticket_mode: TicketMode,
+    pub lamports_per_ticket: u64, // unused in Flat mode
+    pub max_tickets_per_entry: u64,
+    pub min_pot: u64,     // smallest pot that gets drawn; smaller ones roll over
+    pub min_entries: u64, // fewest live entries that get drawn
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PoolConfigParams {
+    pub cooldown_secs: i64,
+    pub claim_window_secs: i64,
+    pub draw_timeout_secs: i64,
+    pub tier_shares_bps: [u16; PRIZE_TIERS],
+    pub consolation_share_bps: u16,
+    pub consolation_winners: u8,
+    pub creator_fee_bps: u16,
+    pub tip_fee_bps: u16,
+    pub min_contribution: u64,
+    pub max_contribution: u64,
+    pub ticket_mode: TicketMode,
+    pub lamports_per_ticket: u64,
+    pub max_tickets_per_entry: u64,
+    pub min_pot: u64,
+    pub min_entries: u64,
+}
+
+// How a pool_contribution turns into draw tickets. Flat gives every entry
+// one ticket; Linear and Sqrt scale with contribution / lamports_per_ticket,
+// capped at max_tickets_per_entry.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum TicketMode {
+    Flat,
+    Linear,
+    Sqrt,
+}
+
+// One per weekly draw, seeded by pool and round_id. Once drawn it is the
+// audit record: sha256(seed) equals draw_commitment, and each winner's ticket
+// follows from seed, entropy and the winner's position.
+#[account]
+#[derive(InitSpace)]
+pub struct RoundState {
+    pub pool: Pubkey,
+    pub round_id: u64,
+    pub started_at: i64,
+    pub ticket_count: u64, // tickets handed out, including voided ones
+    pub entropy: [u8; 32], // running hash of this round's entries
+    pub draw_commitment: Option<[u8; 32]>,
+    pub seed: [u8; 32],
+    #[max_len(10)]
+    pub winners: Vec<RoundWinner>, // in prize order
+    pub entry_count: u64,
+    pub drawn_at: i64,     // 0 while the round is open
+    pub cancelled: bool,   // closed without a draw; its entries can be refunded
+    pub rolled_over: bool, // missed min_pot or min_entries; the pot moved on undrawn
+    pub reveal_slot: u64,  // set by lock_draw; its slot hash is mixed into entropy
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct RoundWinner {
+    pub entry: Pubkey,
+    pub owner: Pubkey,
+    pub ticket: u64, // the ticket that won, after redrawing past void/won ranges
+    pub prize: u64,
+}
+
+#[event]
+pub struct AuthorTipped {
+    pub entry: Pubkey,
+    pub author: Pubkey,
+    pub tipper: Pubkey,
+    pub amount: u64,
+    pub platform_cut: u64,
+    pub timestamp: i64,
+}
+
+fn is_draw_authority(pool: &WeeklyPool, key: &Pubkey) -> bool {
+    pool.creator == *key || pool.cranks.contains(key)
+}
+
+fn open_round(round: &mut RoundState, pool: Pubkey, round_id: u64, started_at: i64) {
+    round.pool = pool;
+    round.round_id = round_id;
+    round.started_at = started_at;
+    round.ticket_count = 0;
+    round.entropy = [0; 32];
+    round.draw_commitment = None;
+    round.seed = [0; 32];
+    round.winners = Vec::new();
+    round.entry_count = 0;
+    round.drawn_at = 0;
+    round.cancelled = false;
+    round.rolled_over = false;
+    round.reveal_slot = 0;
+}
+
+// Moves the pool on to its next round_id once the open round is drawn,
+// rolled over or cancelled; the caller opens the matching RoundState.
+fn advance_round(pool: &mut WeeklyPool, now: i64) -> Result<()> {
+    pool.total_entries = 0;
+    pool.round_contributions = 0;
+    pool.last_spin_timestamp = now;
+    pool.round_id = pool
+        .round_id
+        .checked_add(1)
+        .ok_or(CustomError::MathOverflow)?;
+    Ok(())
+}
 
+// A round that locked but still hasn't been drawn draw_timeout_secs later is
+// given up on: it can no longer be drawn and anyone may cancel it.
+fn draw_timed_out(pool: &WeeklyPool, config: &PoolConfig, now: i64) -> bool {
+    pool.total_entries > 0
+        && now.saturating_sub(pool.last_spin_timestamp)
+            >= config
+                .cooldown_secs
+                .saturating_add(config.draw_timeout_secs)
+}
+
+// Entries lock once the round's cooldown has run out, but only if somebody
+// actually entered; an empty round stays open until it has an entry.
+fn entries_locked(pool: &WeeklyPool, config: &PoolConfig, now: i64) -> bool {
+    pool.total_entries > 0 && now.saturating_sub(pool.last_spin_timestamp) >= config.cooldown_secs
+}
+
+// Creates a program-owned PDA. create_account fails if the address already
+// holds lamports, which anyone can send it, so a funded address is topped up
+// to rent and then allocated and assigned instead.
+fn create_pda_account<'info>(
+    system_program: &Program<'info, System>,
+    payer: &AccountInfo<'info>,
+    target: &AccountInfo<'info>,
+    rent: u64,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let system_program = system_program.to_account_info();
+    let current = target.lamports();
+    if current == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program,
+                CreateAccount {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+                &[signer_seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    if current < rent {
         transfer(
             CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
+                system_program.clone(),
                 Transfer {
-                    from: owner.to_account_info(),
-                    to: ctx.accounts.weekly_pool.to_account_info(),
+                    from: payer.clone(),
+                    to: target.clone(),
                 },
             ),
-            lamports,
+            rent - current,
         )?;
+    }
+    allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Allocate {
+                account_to_allocate: target.clone(),
+            },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    assign(
+        CpiContext::new_with_signer(
+            system_program,
+            Assign {
+                account_to_assign: target.clone(),
+            },
+            &[signer_seeds],
+        ),
+        &crate::ID,
+    )
+}
 
-        // increment total pool
-        let pool = &mut ctx.accounts.weekly_pool;
-        pool.total_pool += lamports;
-        pool.total_entries += 1;
+// Reads a program account that may not exist, or may have been closed.
+fn load_if_open<T: AccountDeserialize>(info: &AccountInfo) -> Result<Option<T>> {
+    if info.owner != &crate::ID {
+        return Ok(None);
+    }
+    T::try_deserialize(&mut &info.try_borrow_data()?[..]).map(Some)
+}
 
-        Ok(())
+// Applies `update` to a program account that may have been closed earlier,
+// e.g. an entry deleted before its reactions. A closed account belongs to the
+// system program again, so there is nothing left to keep in sync.
+fn update_if_open<T: AccountSerialize + AccountDeserialize>(
+    info: &AccountInfo,
+    update: impl FnOnce(&mut T),
+) -> Result<()> {
+    if info.owner != &crate::ID {
+        return Ok(());
     }
+    let mut data = info.try_borrow_mut_data()?;
+    let mut state = T::try_deserialize(&mut &data[..])?;
+    update(&mut state);
+    state.try_serialize(&mut &mut data[..])
+}
 
-    pub fn declare_winner(ctx: Context<DeclareWinner>) -> Result<()> {
-        let pool = &mut ctx.accounts.weekly_pool;
-        require!(pool.total_entries > 0, CustomError::NoEntries);
+fn init_treasury(treasury: &mut Treasury, pool: Pubkey) {
+    treasury.pool = pool;
+    treasury.accrued = 0;
+    treasury.total_withdrawn = 0;
+    treasury.beneficiaries = Vec::new();
+}
 
-        let now = Clock::get()?.unix_timestamp;
+fn init_pool(pool: &mut WeeklyPool, pool_id: u64, creator: Pubkey, mint: Option<Pubkey>, now: i64) {
+    pool.pool_id = pool_id;
+    pool.creator = creator;
+    pool.mint = mint;
+    pool.total_pool = 0;
+    pool.total_entries = 0;
+    pool.last_winner_number = 0;
+    // the first round starts now so entries lock a cooldown from here
+    pool.last_spin_timestamp = now;
+    pool.round_id = 0;
+    pool.cranks = Vec::new();
+    pool.reserved_prizes = 0;
+    pool.round_contributions = 0;
+    pool.reserved_refunds = 0;
+    pool.rollover_count = 0;
+    pool.jackpot_carried = 0;
+    pool.sponsorship_count = 0;
+    pool.campaign_count = 0;
+    pool.reserved_matching = 0;
+}
 
-        // Check if last spin was within 7 days (7 * 24 * 60 * 60 seconds)
-        if pool.last_spin_timestamp != 0 && now - pool.last_spin_timestamp < SPIN_COOLDOWN_SECS {
-            return Err(error!(CustomError::SpinTooEarly));
-        }
+// Takes a deposit into the pool: lamports into the pool account for SOL
+// pools, or tokens into the vault for token pools.
+#[allow(clippy::too_many_arguments)]
+fn deposit_to_pool<'info>(
+    pool: &Account<'info, WeeklyPool>,
+    depositor: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    mint: &Option<InterfaceAccount<'info, Mint>>,
+    from: &Option<InterfaceAccount<'info, TokenAccount>>,
+    vault: &Option<InterfaceAccount<'info, TokenAccount>>,
+    token_program: &Option<Interface<'info, TokenInterface>>,
+    amount: u64,
+) -> Result<()> {
+    if pool.mint.is_none() {
+        return transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: depositor.to_account_info(),
+                    to: pool.to_account_info(),
+                },
+            ),
+            amount,
+        );
+    }
 
-        // Validate the selected blog actually matches the chosen number
-        let blog = &ctx.accounts.winner_blog;
+    let (Some(mint), Some(from), Some(vault), Some(token_program)) =
+        (mint, from, vault, token_program)
+    else {
+        return Err(error!(CustomError::TokenAccountsRequired));
+    };
+    transfer_checked(
+        CpiContext::new(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: vault.to_account_info(),
+                authority: depositor.to_account_info(),
+            },
+        ),
+        amount,
+        mint.decimals,
+    )
+}
 
-        // Validate the winner pubkey matches the blog owner (defense-in-depth)
-        if ctx.accounts.winner.key() != blog.owner {
-            return Err(error!(CustomError::WinnerMismatch));
-        }
+// Moves lamports out of a program-owned account (the pool or its treasury),
+// refusing to dip into the balance that keeps it rent-exempt.
+fn pay_from_pool(pool: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let rent_floor = Rent::get()?.minimum_balance(pool.data_len());
+    let remaining = pool
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(CustomError::MathOverflow)?;
+    require!(remaining >= rent_floor, CustomError::PoolBelowRent);
+    let received = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(CustomError::MathOverflow)?;
 
-        let winner_share = pool.total_pool * 90 / 100;
-        let owner_share = pool.total_pool - winner_share;
-
-        // Send 90% to winner
-        **ctx
-            .accounts
-            .winner
-            .to_account_info()
-            .try_borrow_mut_lamports()? += winner_share;
-        **pool.to_account_info().try_borrow_mut_lamports()? -= winner_share;
-
-        // Send 10% to admin
-        **ctx
-            .accounts
-            .creator_wallet
-            .to_account_info()
-            .try_borrow_mut_lamports()? += owner_share;
-        **pool.to_account_info().try_borrow_mut_lamports()? -= owner_share;
-
-        pool.total_pool = 0;
-        pool.total_entries = 0;
-        pool.last_winner_number = blog.random_number;
-        pool.last_spin_timestamp = now;
+    **pool.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? = received;
+    Ok(())
+}
 
-        msg!(
-            "Winner blog: {:?}, random_number: {}",
-            blog.title,
-            blog.random_number
-        );
+// Pays tokens out of a token pool's vault; the pool PDA signs as the vault's
+// authority. The optional accounts must all be present for token pools.
+fn pay_from_vault<'info>(
+    pool: &Account<'info, WeeklyPool>,
+    pool_bump: u8,
+    mint: &Option<InterfaceAccount<'info, Mint>>,
+    vault: &Option<InterfaceAccount<'info, TokenAccount>>,
+    to: &Option<InterfaceAccount<'info, TokenAccount>>,
+    token_program: &Option<Interface<'info, TokenInterface>>,
+    amount: u64,
+) -> Result<()> {
+    let (Some(mint), Some(vault), Some(to), Some(token_program)) = (mint, vault, to, token_program)
+    else {
+        return Err(error!(CustomError::TokenAccountsRequired));
+    };
 
-        Ok(())
-    }
+    let pool_id = pool.pool_id.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[b"weekly_pool_data", &pool_id, &[pool_bump]]];
+    transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: vault.to_account_info(),
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+        mint.decimals,
+    )
 }
 
-#[derive(Accounts)]
-#[instruction(title: String)]
-pub struct CreateEntry<'info> {
-    #[account(
-        init,
-        seeds = [title.as_bytes(), owner.key().as_ref()],
-        bump,
-        space = 8 + BlogEntryState::INIT_SPACE,
-        payer = owner
-    )]
-    pub blog_entry: Account<'info, BlogEntryState>,
-
-    #[account(mut)]
-    pub owner: Signer<'info>,
+fn apply_bps(amount: u64, bps: u16) -> u64 {
+    (amount as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
+}
 
-    #[account(
-        mut,
-        seeds = [b"weekly_pool_data"],
-        bump
-    )]
-    pub weekly_pool: Account<'info, WeeklyPool>,
+// Returns (author_share, platform_cut). Tips are always SOL, so a token pool
+// can't take its cut.
+fn split_tip(mint: Option<Pubkey>, tip_fee_bps: u16, amount: u64) -> (u64, u64) {
+    let platform_cut = match mint {
+        None => apply_bps(amount, tip_fee_bps),
+        Some(_) => 0,
+    };
+    (amount.saturating_sub(platform_cut), platform_cut)
+}
 
-    pub system_program: Program<'info, System>,
+fn validate_config(params: &PoolConfigParams) -> Result<()> {
+    require!(
+        params.cooldown_secs >= MIN_COOLDOWN_SECS && params.cooldown_secs <= MAX_COOLDOWN_SECS,
+        CustomError::InvalidConfig
+    );
+    require!(
+        params.claim_window_secs >= MIN_CLAIM_WINDOW_SECS
+            && params.claim_window_secs <= MAX_CLAIM_WINDOW_SECS,
+        CustomError::InvalidConfig
+    );
+    require!(
+        params.draw_timeout_secs >= MIN_DRAW_TIMEOUT_SECS
+            && params.draw_timeout_secs <= MAX_DRAW_TIMEOUT_SECS,
+        CustomError::InvalidConfig
+    );
+    require!(
+        params.creator_fee_bps <= MAX_CREATOR_FEE_BPS,
+        CustomError::InvalidConfig
+    );
+    require!(params.tier_shares_bps[0] > 0, CustomError::InvalidConfig);
+    require!(
+        params.consolation_winners <= MAX_CONSOLATION_WINNERS
+            && (params.consolation_winners > 0) == (params.consolation_share_bps > 0),
+        CustomError::InvalidConfig
+    );
+    let total_bps = params
+        .tier_shares_bps
+        .iter()
+        .map(|bps| *bps as u64)
+        .sum::<u64>()
+        + params.consolation_share_bps as u64
+        + params.creator_fee_bps as u64;
+    require!(total_bps <= BPS_DENOMINATOR, CustomError::InvalidConfig);
+    require!(
+        params.tip_fee_bps <= MAX_TIP_FEE_BPS,
+        CustomError::TipFeeTooHigh
+    );
+    require!(
+        params.max_contribution > 0 && params.min_contribution <= params.max_contribution,
+        CustomError::InvalidConfig
+    );
+    if params.ticket_mode != TicketMode::Flat {
+        require!(
+            params.lamports_per_ticket > 0 && params.max_tickets_per_entry > 0,
+            CustomError::InvalidConfig
+        );
+    }
+    Ok(())
 }
 
-#[derive(Accounts)]
-#[instruction(title: String)]
-pub struct UpdateEntry<'info> {
-    #[account(
-        mut,
-        seeds = [title.as_bytes(), owner.key().as_ref()],
-        bump,
-        realloc = 8 + BlogEntryState::INIT_SPACE,
-        realloc::payer = owner,
-        realloc::zero = true
-    )]
-    pub blog_entry: Account<'info, BlogEntryState>,
+fn apply_config(config: &mut PoolConfig, params: &PoolConfigParams) {
+    config.cooldown_secs = params.cooldown_secs;
+    config.claim_window_secs = params.claim_window_secs;
+    config.draw_timeout_secs = params.draw_timeout_secs;
+    config.tier_shares_bps = params.tier_shares_bps;
+    config.consolation_share_bps = params.consolation_share_bps;
+    config.consolation_winners = params.consolation_winners;
+    config.creator_fee_bps = params.creator_fee_bps;
+    config.tip_fee_bps = params.tip_fee_bps;
+    config.min_contribution = params.min_contribution;
+    config.max_contribution = params.max_contribution;
+    config.ticket_mode = params.ticket_mode;
+    config.lamports_per_ticket = params.lamports_per_ticket;
+    config.max_tickets_per_entry = params.max_tickets_per_entry;
+    config.min_pot = params.min_pot;
+    config.min_entries = params.min_entries;
+}
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
+// Every entry gets at least one ticket, whatever it contributed.
+fn tickets_for(config: &PoolConfig, contribution: u64) -> u64 {
+    let tickets = match config.ticket_mode {
+        TicketMode::Flat => return 1,
+        TicketMode::Linear => contribution / config.lamports_per_ticket,
+        TicketMode::Sqrt => isqrt(contribution / config.lamports_per_ticket),
+    };
+    tickets.clamp(1, config.max_tickets_per_entry)
+}
 
-    pub system_program: Program<'info, System>,
+fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's method, starting above the root so it converges downwards
+    let mut x = n / 2 + 1;
+    let mut y = (x + n / x) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
 }
 
-#[derive(Accounts)]
-#[instruction(title: String)]
-pub struct DeleteEntry<'info> {
-    #[account(
-        mut,
-        seeds = [title.as_bytes(), owner.key().as_ref()],
-        bump,
-        close = owner
-    )]
-    pub blog_entry: Account<'info, BlogEntryState>,
+// SlotHashes holds (slot, hash) pairs newest first. Returns the hash of the
+// first slot at or after `target`, which is `target` itself unless it was
+// skipped.
+fn slot_hash_at_or_after(data: &[u8], target: u64) -> Result<[u8; 32]> {
+    let mut len = [0u8; 8];
+    len.copy_from_slice(data.get(..8).ok_or(CustomError::InvalidSlotHashes)?);
+    let mut found = None;
+    for pair in data[8..]
+        .chunks_exact(40)
+        .take(u64::from_le_bytes(len) as usize)
+    {
+        let mut slot = [0u8; 8];
+        slot.copy_from_slice(&pair[..8]);
+        if u64::from_le_bytes(slot) < target {
+            return found.ok_or_else(|| error!(CustomError::RevealSlotNotReached));
+        }
+        let mut slot_hash = [0u8; 32];
+        slot_hash.copy_from_slice(&pair[8..]);
+        found = Some(slot_hash);
+    }
+    // every slot still in the sysvar is past target, so its hash is gone
+    err!(CustomError::RevealSlotExpired)
+}
 
-    #[account(mut)]
-    pub owner: Signer<'info>,
+// First 8 bytes of sha256(seed || entropy || slot || attempt), reduced to a
+// ticket number. slot is the winner's position in prize order and attempt
+// counts redraws after landing on a void or already-won range.
+fn winning_ticket(
+    seed: &[u8; 32],
+    entropy: &[u8; 32],
+    slot: u64,
+    attempt: u64,
+    ticket_count: u64,
+) -> u64 {
+    let digest = hashv(&[seed, entropy, &slot.to_le_bytes(), &attempt.to_le_bytes()]).to_bytes();
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&digest[..8]);
+    u64::from_le_bytes(value) % ticket_count
+}
 
-    pub system_program: Program<'info, System>,
+// Prize for each winner in draw order: the non-zero tiers first, then the
+// consolation winners with an equal cut each.
+fn prize_slots(config: &PoolConfig, pot: u64) -> Vec<u64> {
+    let mut prizes: Vec<u64> = config
+        .tier_shares_bps
+        .iter()
+        .filter(|bps| **bps > 0)
+        .map(|bps| apply_bps(pot, *bps))
+        .collect();
+    if config.consolation_winners > 0 {
+        let each = apply_bps(pot, config.consolation_share_bps) / config.consolation_winners as u64;
+        prizes.extend(std::iter::repeat_n(
+            each,
+            config.consolation_winners as usize,
+        ));
+    }
+    prizes
 }
 
-#[derive(Accounts)]
-pub struct DeclareWinner<'info> {
-    #[account(mut, seeds = [b"weekly_pool_data"], bump)]
-    pub weekly_pool: Account<'info, WeeklyPool>,
+// Draws tickets for one prize slot until one lands on a live entry, taking
+// accounts from the remaining accounts as it goes. A ticket in a range that
+// is already in `skipped` (won this draw, or voided and verified earlier) is
+// redrawn straight away; one in a new voided range is redrawn after checking
+// its VoidTicket, whose range is then added to `skipped`. Returns the entry's
+// account, its data and the ticket it won with.
+fn find_winner<'a, 'info>(
+    accounts: &mut std::slice::Iter<'a, AccountInfo<'info>>,
+    round: &RoundState,
+    seed: &[u8; 32],
+    slot: u64,
+    skipped: &mut Vec<(u64, u64)>,
+) -> Result<(&'a AccountInfo<'info>, BlogEntryState, u64)> {
+    let mut attempt = 0u64;
+    loop {
+        let ticket = winning_ticket(seed, &round.entropy, slot, attempt, round.ticket_count);
+        attempt = attempt.checked_add(1).ok_or(CustomError::MathOverflow)?;
+        if skipped
+            .iter()
+            .any(|(start, end)| (*start..*end).contains(&ticket))
+        {
+            continue;
+        }
 
-    pub winner_blog: Account<'info, BlogEntryState>,
+        let info = accounts.next().ok_or(CustomError::WinnerNotDrawn)?;
+        require_keys_eq!(*info.owner, crate::ID, CustomError::WinnerNotDrawn);
+        let data = info.try_borrow_data()?;
 
-    /// CHECK: winner wallet address found off-chain based on lucky number
-    #[account(mut)]
-    pub winner: UncheckedAccount<'info>,
+        if data.starts_with(VoidTicket::DISCRIMINATOR) {
+            let void_ticket = VoidTicket::try_deserialize(&mut &data[..])?;
+            require!(
+                void_ticket.pool == round.pool
+                    && void_ticket.round_id == round.round_id
+                    && (void_ticket.ticket_start..void_ticket.ticket_end).contains(&ticket),
+                CustomError::WinnerNotDrawn
+            );
+            skipped.push((void_ticket.ticket_start, void_ticket.ticket_end));
+            continue;
+        }
 
-    /// CHECK: admin/creator wallet
-    #[account(mut)]
-    pub creator_wallet: UncheckedAccount<'info>,
+        // Validate the blog was entered in this round and holds the ticket
+        let blog = BlogEntryState::try_deserialize(&mut &data[..])?;
+        require!(
+            blog.pool == round.pool && blog.round_id == round.round_id,
+            CustomError::InvalidWinner
+        );
+        require!(
+            (blog.ticket_start..blog.ticket_start.saturating_add(blog.tickets)).contains(&ticket),
+            CustomError::WinnerNotDrawn
+        );
+        return Ok((info, blog, ticket));
+    }
+}
 
-    pub system_program: Program<'info, System>,
+// Token-2022 extensions that let the vault balance drift from total_pool:
+// fees skim transfers, hooks run foreign code on them and a permanent
+// delegate can move vault tokens at will.
+fn validate_mint(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension in state.get_extension_types()? {
+        require!(
+            !matches!(
+                extension,
+                ExtensionType::TransferFeeConfig
+                    | ExtensionType::TransferHook
+                    | ExtensionType::PermanentDelegate
+            ),
+            CustomError::UnsupportedMintExtension
+        );
+    }
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct InitializePool<'info> {
-    #[account(
-        init,
-        payer = creator,
-        space = 8 + WeeklyPool::INIT_SPACE,
-        seeds = [b"weekly_pool_data"],
-        bump
-    )]
-    pub weekly_pool: Account<'info, WeeklyPool>,
+fn validate_handle(handle: &str) -> Result<()> {
+    require!(!handle.is_empty(), CustomError::HandleEmpty);
+    require!(handle.len() <= MAX_HANDLE_LEN, CustomError::HandleTooLong);
+    for c in handle.bytes() {
+        require!(!c.is_ascii_uppercase(), CustomError::HandleNotLowercase);
+        require!(
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'_',
+            CustomError::HandleInvalidCharacter
+        );
+    }
+    Ok(())
+}
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
+fn validate_tag(tag: &str) -> Result<()> {
+    require!(
+        !tag.is_empty() && tag.len() <= MAX_TAG_LEN,
+        CustomError::InvalidTag
+    );
+    require!(
+        tag.bytes()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-'),
+        CustomError::InvalidTag
+    );
+    Ok(())
+}
 
-    pub system_program: Program<'info, System>,
+fn validate_comment(body: &str, body_cid: &str) -> Result<()> {
+    require!(
+        !body.is_empty() || !body_cid.is_empty(),
+        CustomError::EmptyComment
+    );
+    require!(body.len() <= MAX_COMMENT_LEN, CustomError::CommentTooLong);
+    require!(
+        body_cid.len() <= MAX_IPFS_HASH_LEN,
+        CustomError::IpfsHashTooLong
+    );
+    Ok(())
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct BlogEntryState {
-    pub owner: Pubkey,
-    #[max_len(50)]
-    pub title: String,
-    #[max_len(100)]
-    pub ipfs_hash: String,
-    pub random_number: u32,
-    pub created_at: i64,
-    pub tip: u64,
+fn validate_profile(bio: &str, avatar_cid: &str) -> Result<()> {
+    require!(bio.len() <= MAX_BIO_LEN, CustomError::BioTooLong);
+    require!(
+        avatar_cid.len() <= MAX_IPFS_HASH_LEN,
+        CustomError::IpfsHashTooLong
+    );
+    Ok(())
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct WeeklyPool {
-    pub creator: Pubkey,
-    pub total_pool: u64,
-    pub total_entries: u64,
-    pub last_winner_number: u32,
-    pub last_spin_timestamp: i64,
+fn validate_sponsorship(name: &str, cid: &str) -> Result<()> {
+    require!(!name.is_empty(), CustomError::SponsorNameEmpty);
+    require!(
+        name.len() <= MAX_SPONSOR_NAME_LEN,
+        CustomError::SponsorNameTooLong
+    );
+    require!(cid.len() <= MAX_IPFS_HASH_LEN, CustomError::IpfsHashTooLong);
+    Ok(())
 }
 
 #[error_code]
 pub enum CustomError {
     #[msg("No entries found in the pool")]
     NoEntries,
-    #[msg("You must wait 7 days between winner declarations")]
+    #[msg("You must wait for the cooldown between winner declarations")]
     SpinTooEarly,
     #[msg("Chosen winner does not match any blog entry")]
     InvalidWinner,
     #[msg("Winner pubkey doesn't match blog owner")]
     WinnerMismatch,
+    #[msg("Signer doesn't own this account")]
+    Unauthorized,
+    #[msg("IPFS hash exceeds the maximum length")]
+    IpfsHashTooLong,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Title exceeds the maximum length")]
+    TitleTooLong,
+    #[msg("Account is not a legacy blog entry")]
+    InvalidLegacyEntry,
+    #[msg("Handle exceeds the maximum length")]
+    HandleTooLong,
+    #[msg("Bio exceeds the maximum length")]
+    BioTooLong,
+    #[msg("Handle cannot be empty")]
+    HandleEmpty,
+    #[msg("Handle must be lowercase")]
+    HandleNotLowercase,
+    #[msg("Handle may only contain a-z, 0-9 and '_'")]
+    HandleInvalidCharacter,
+    #[msg("Profile already has a handle")]
+    HandleAlreadySet,
+    #[msg("Tags must be 1-32 characters of a-z, 0-9 and '-'")]
+    InvalidTag,
+    #[msg("Entry already has the maximum number of tags")]
+    TooManyTags,
+    #[msg("Entry already carries this tag")]
+    TagAlreadyAdded,
+    #[msg("Tag page is full, add a new page first")]
+    TagPageFull,
+    #[msg("Tag page still has room")]
+    TagPageNotFull,
+    #[msg("Entry not found on this tag page")]
+    TagNotFound,
+    #[msg("Comment needs a body or a CID")]
+    EmptyComment,
+    #[msg("Comment exceeds the maximum length")]
+    CommentTooLong,
+    #[msg("Parent comment belongs to a different entry")]
+    InvalidParentComment,
+    #[msg("Tip amount must be greater than zero")]
+    InvalidTipAmount,
+    #[msg("Tip fee exceeds the allowed maximum")]
+    TipFeeTooHigh,
+    #[msg("Author account doesn't match blog owner")]
+    AuthorMismatch,
+    #[msg("Entries are locked until the current round is drawn")]
+    RoundLocked,
+    #[msg("A draw commitment already exists for this round")]
+    CommitmentAlreadySet,
+    #[msg("No draw commitment for this round")]
+    NoCommitment,
+    #[msg("Seed does not match the committed hash")]
+    InvalidReveal,
+    #[msg("Winner blog does not hold the drawn ticket")]
+    WinnerNotDrawn,
+    #[msg("Deleting an entry in the open round requires its void ticket")]
+    VoidTicketRequired,
+    #[msg("Crank is already registered")]
+    CrankAlreadyAdded,
+    #[msg("Maximum number of cranks reached")]
+    TooManyCranks,
+    #[msg("Crank is not registered")]
+    CrankNotFound,
+    #[msg("Pool config values are out of bounds")]
+    InvalidConfig,
+    #[msg("Pool contribution is outside the configured range")]
+    ContributionOutOfRange,
+    #[msg("Prize claim account does not match the winning entry")]
+    InvalidPrizeClaim,
+    #[msg("The claim window for this prize has passed")]
+    ClaimExpired,
+    #[msg("The prize can still be claimed")]
+    ClaimNotExpired,
+    #[msg("Payout would leave the pool below its rent-exempt minimum")]
+    PoolBelowRent,
+    #[msg("Account belongs to a different pool")]
+    PoolMismatch,
+    #[msg("Mint does not match the pool's mint")]
+    InvalidMint,
+    #[msg("Token pools need the mint, vault and token accounts")]
+    TokenAccountsRequired,
+    #[msg("The round is past its draw timeout and can only be cancelled")]
+    DrawTimedOut,
+    #[msg("Only the pool authority can cancel, and only before the reveal slot, unless the reveal expired or the draw timed out")]
+    CancelNotAllowed,
+    #[msg("The entry's round was not cancelled")]
+    RoundNotCancelled,
+    #[msg("Entry has already been refunded")]
+    AlreadyRefunded,
+    #[msg("Deposit amount must be greater than zero")]
+    InvalidDepositAmount,
+    #[msg("Sponsor name cannot be empty")]
+    SponsorNameEmpty,
+    #[msg("Sponsor name is too long")]
+    SponsorNameTooLong,
+    #[msg("Campaign account or parameters are invalid")]
+    InvalidCampaign,
+    #[msg("The matching campaign has ended")]
+    CampaignEnded,
+    #[msg("The matching campaign is still running")]
+    CampaignActive,
+    #[msg("Beneficiary weights or accounts are invalid")]
+    InvalidBeneficiaries,
+    #[msg("The treasury has no fees to withdraw")]
+    NoFeesAccrued,
+    #[msg("Remove the entry from its tags before deleting it")]
+    EntryStillTagged,
+    #[msg("Account is not a legacy weekly pool")]
+    InvalidLegacyPool,
+    #[msg("The draw must be committed before the round's first entry")]
+    CommitAfterEntries,
+    #[msg("The draw for this round is already locked")]
+    DrawAlreadyLocked,
+    #[msg("Entries are still open for this round")]
+    EntriesStillOpen,
+    #[msg("Call lock_draw before declaring the winner")]
+    DrawNotLocked,
+    #[msg("Malformed SlotHashes sysvar data")]
+    InvalidSlotHashes,
+    #[msg("The locked reveal slot hasn't been reached yet")]
+    RevealSlotNotReached,
+    #[msg("The reveal slot's hash has expired; cancel the round")]
+    RevealSlotExpired,
+    #[msg("Mints with transfer fees, transfer hooks or a permanent delegate aren't supported")]
+    UnsupportedMintExtension,
+    #[msg("The entry's round account is required")]
+    RoundRequired,
+    #[msg("Take the refund for this entry before deleting it")]
+    RefundPending,
+    #[msg("Only the pool creator, or a crank where allowed, can do this")]
+    NotPoolAuthority,
 }
 
 //* #[account] -> It only tells Anchor: This struct is meant to be stored inside a Solana account, and Anchor should serialize/deserialize it. It makes your struct: Use Anchor's Borsh serialization \n Get an automatic 8-byte discriminator\n Become eligible to be used in account constraints like: pub blog: Account<'info, BlogEntryState>  But it does not create the account./
@@ -296,3 +3812,358 @@ pub enum CustomError {
 
     This account will be modified, so mark it mutable.”
  */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(ticket_mode: TicketMode, max_tickets_per_entry: u64) -> PoolConfig {
+        PoolConfig {
+            cooldown_secs: MIN_COOLDOWN_SECS,
+            claim_window_secs: MIN_CLAIM_WINDOW_SECS,
+            draw_timeout_secs: MIN_DRAW_TIMEOUT_SECS,
+            tier_shares_bps: [5_000, 0, 0],
+            consolation_share_bps: 0,
+            consolation_winners: 0,
+            creator_fee_bps: 0,
+            tip_fee_bps: 0,
+            min_contribution: 0,
+            max_contribution: u64::MAX,
+            ticket_mode,
+            lamports_per_ticket: 10,
+            max_tickets_per_entry,
+            min_pot: 0,
+            min_entries: 0,
+        }
+    }
+
+    fn params(config: &PoolConfig) -> PoolConfigParams {
+        PoolConfigParams {
+            cooldown_secs: config.cooldown_secs,
+            claim_window_secs: config.claim_window_secs,
+            draw_timeout_secs: config.draw_timeout_secs,
+            tier_shares_bps: config.tier_shares_bps,
+            consolation_share_bps: config.consolation_share_bps,
+            consolation_winners: config.consolation_winners,
+            creator_fee_bps: config.creator_fee_bps,
+            tip_fee_bps: config.tip_fee_bps,
+            min_contribution: config.min_contribution,
+            max_contribution: config.max_contribution,
+            ticket_mode: config.ticket_mode,
+            lamports_per_ticket: config.lamports_per_ticket,
+            max_tickets_per_entry: config.max_tickets_per_entry,
+            min_pot: config.min_pot,
+            min_entries: config.min_entries,
+        }
+    }
+
+    #[test]
+    fn isqrt_boundaries() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(2), 1);
+        assert_eq!(isqrt(3), 1);
+        assert_eq!(isqrt(4), 2);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(17), 4);
+        let root = u32::MAX as u64;
+        assert_eq!(isqrt(root * root - 1), root - 1);
+        assert_eq!(isqrt(root * root), root);
+        assert_eq!(isqrt(u64::MAX), root);
+    }
+
+    #[test]
+    fn tickets_are_clamped() {
+        let flat = config(TicketMode::Flat, 0);
+        assert_eq!(tickets_for(&flat, 0), 1);
+        assert_eq!(tickets_for(&flat, u64::MAX), 1);
+
+        let linear = config(TicketMode::Linear, 50);
+        assert_eq!(tickets_for(&linear, 0), 1);
+        assert_eq!(tickets_for(&linear, 9), 1);
+        assert_eq!(tickets_for(&linear, 250), 25);
+        assert_eq!(tickets_for(&linear, 500), 50);
+        assert_eq!(tickets_for(&linear, u64::MAX), 50);
+
+        let sqrt = config(TicketMode::Sqrt, 50);
+        assert_eq!(tickets_for(&sqrt, 0), 1);
+        assert_eq!(tickets_for(&sqrt, 160), 4);
+        assert_eq!(tickets_for(&sqrt, 25_000), 50);
+        assert_eq!(tickets_for(&sqrt, u64::MAX), 50);
+    }
+
+    #[test]
+    fn prizes_never_exceed_pot_minus_fee() {
+        let shapes: [([u16; PRIZE_TIERS], u16, u8, u16); 5] = [
+            ([10_000, 0, 0], 0, 0, 0),
+            ([8_000, 0, 0], 0, 0, MAX_CREATOR_FEE_BPS),
+            ([5_000, 2_000, 1_000], 1_000, MAX_CONSOLATION_WINNERS, 1_000),
+            ([3_333, 3_333, 3_333], 0, 0, 1),
+            ([1, 1, 1], 7_997, 3, MAX_CREATOR_FEE_BPS),
+        ];
+        let pots = [0, 1, 7, 9_999, 10_001, 1_000_000_007, u64::MAX];
+
+        for (tiers, consolation_bps, consolation_winners, fee_bps) in shapes {
+            let mut config = config(TicketMode::Flat, 0);
+            config.tier_shares_bps = tiers;
+            config.consolation_share_bps = consolation_bps;
+            config.consolation_winners = consolation_winners;
+            config.creator_fee_bps = fee_bps;
+            validate_config(&params(&config)).unwrap();
+
+            for pot in pots {
+                let prizes: u128 = prize_slots(&config, pot).iter().map(|p| *p as u128).sum();
+                let fee = apply_bps(pot, fee_bps) as u128;
+                assert!(prizes + fee <= pot as u128, "{tiers:?} {pot}");
+            }
+        }
+    }
+
+    #[test]
+    fn config_rejects_shares_over_the_pot() {
+        let mut over = config(TicketMode::Flat, 0);
+        over.tier_shares_bps = [9_000, 0, 0];
+        over.creator_fee_bps = 1_001;
+        assert!(validate_config(&params(&over)).is_err());
+
+        let mut unpaid = config(TicketMode::Flat, 0);
+        unpaid.consolation_winners = 2;
+        assert!(validate_config(&params(&unpaid)).is_err());
+    }
+
+    #[test]
+    fn slot_hash_lookup() {
+        // newest first: slots 12, 10 (11 skipped), 9
+        let mut data = 3u64.to_le_bytes().to_vec();
+        for (slot, byte) in [(12u64, 3u8), (10, 2), (9, 1)] {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[byte; 32]);
+        }
+        assert_eq!(slot_hash_at_or_after(&data, 10).unwrap(), [2; 32]);
+        assert_eq!(slot_hash_at_or_after(&data, 11).unwrap(), [3; 32]);
+        assert!(slot_hash_at_or_after(&data, 13).is_err());
+        assert!(slot_hash_at_or_after(&data, 9).is_err());
+    }
+
+    fn entry_data(pool: Pubkey, ticket_start: u64, tickets: u64) -> Vec<u8> {
+        let entry = BlogEntryState {
+            owner: Pubkey::new_unique(),
+            post_index: 0,
+            title: String::new(),
+            ipfs_hash: String::new(),
+            random_number: 0,
+            created_at: 0,
+            tip: 0,
+            updated_at: 0,
+            edit_count: 0,
+            tags: Vec::new(),
+            next_comment_index: 0,
+            comment_count: 0,
+            reaction_counts: [0; REACTION_KIND_COUNT],
+            total_tips_received: 0,
+            pool,
+            round_id: 0,
+            ticket_start,
+            tickets,
+            refunded: false,
+        };
+        let mut data = Vec::new();
+        entry.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn void_data(pool: Pubkey, ticket_start: u64, ticket_end: u64) -> Vec<u8> {
+        let void_ticket = VoidTicket {
+            pool,
+            round_id: 0,
+            ticket_start,
+            ticket_end,
+        };
+        let mut data = Vec::new();
+        void_ticket.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn round(pool: Pubkey, ticket_count: u64, seed: u64) -> RoundState {
+        RoundState {
+            pool,
+            round_id: 0,
+            started_at: 0,
+            ticket_count,
+            entropy: hash(&seed.to_le_bytes()).to_bytes(),
+            draw_commitment: None,
+            seed: [0; 32],
+            winners: Vec::new(),
+            entry_count: 0,
+            drawn_at: 0,
+            cancelled: false,
+            rolled_over: false,
+            reveal_slot: 0,
+        }
+    }
+
+    // Tickets 0 (entry A), 1 (voided) and 2..4 (entry B). Walks each draw
+    // the way a client would, handing find_winner the void ticket the first
+    // time it's hit, and checks the winner against every range rule.
+    #[test]
+    fn winners_skip_void_and_won_ranges() {
+        let pool = Pubkey::new_unique();
+        let keys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut datas = [
+            entry_data(pool, 0, 1),
+            void_data(pool, 1, 2),
+            entry_data(pool, 2, 2),
+        ];
+        let mut lamports = [1u64; 3];
+        let infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(datas.iter_mut())
+            .zip(lamports.iter_mut())
+            .map(|((key, data), lamports)| {
+                AccountInfo::new(key, false, false, lamports, data, &crate::ID, false, 0)
+            })
+            .collect();
+        let (entry_a, void_range, entry_b) = (&infos[0], &infos[1], &infos[2]);
+
+        let seed = [7u8; 32];
+        let mut a_wins = 0;
+        for draw in 0..400u64 {
+            let round = round(pool, 4, draw);
+
+            // first prize: replay the draw to know which accounts it needs
+            let mut expected = Vec::new();
+            for attempt in 0.. {
+                let ticket = winning_ticket(&seed, &round.entropy, 0, attempt, 4);
+                match ticket {
+                    0 => expected.push(entry_a.clone()),
+                    1 if expected.is_empty() => {
+                        expected.push(void_range.clone());
+                        continue;
+                    }
+                    1 => continue,
+                    _ => expected.push(entry_b.clone()),
+                }
+                break;
+            }
+            let mut skipped = Vec::new();
+            let (winner, blog, ticket) =
+                find_winner(&mut expected.iter(), &round, &seed, 0, &mut skipped).unwrap();
+            assert_ne!(ticket, 1);
+            assert!((blog.ticket_start..blog.ticket_start + blog.tickets).contains(&ticket));
+            if winner.key == entry_a.key {
+                a_wins += 1;
+            }
+
+            // second prize: the first winner's range is out, so the other
+            // entry wins, and a void verified for the first prize isn't
+            // passed again
+            skipped.push((blog.ticket_start, blog.ticket_start + blog.tickets));
+            let other = if winner.key == entry_a.key {
+                entry_b
+            } else {
+                entry_a
+            };
+            let mut seen = skipped.clone();
+            let mut accounts = Vec::new();
+            for attempt in 0.. {
+                let ticket = winning_ticket(&seed, &round.entropy, 1, attempt, 4);
+                if seen
+                    .iter()
+                    .any(|(start, end)| (*start..*end).contains(&ticket))
+                {
+                    continue;
+                }
+                if ticket == 1 {
+                    accounts.push(void_range.clone());
+                    seen.push((1, 2));
+                    continue;
+                }
+                accounts.push(other.clone());
+                break;
+            }
+            let (second, _, _) =
+                find_winner(&mut accounts.iter(), &round, &seed, 1, &mut skipped).unwrap();
+            assert_eq!(second.key, other.key);
+        }
+
+        // A holds one of the three live tickets; the voided ticket must not
+        // add to either neighbour's odds
+        assert!((100..170).contains(&a_wins), "{a_wins}");
+    }
+
+    #[test]
+    fn handles_are_lowercase_ascii() {
+        validate_handle("curd_42").unwrap();
+        validate_handle(&"a".repeat(MAX_HANDLE_LEN)).unwrap();
+
+        let err = |handle: &str| validate_handle(handle).unwrap_err();
+        assert_eq!(err(""), CustomError::HandleEmpty.into());
+        assert_eq!(
+            err(&"a".repeat(MAX_HANDLE_LEN + 1)),
+            CustomError::HandleTooLong.into()
+        );
+        assert_eq!(err("Curd"), CustomError::HandleNotLowercase.into());
+        assert_eq!(err("curd-42"), CustomError::HandleInvalidCharacter.into());
+        assert_eq!(err("cürd"), CustomError::HandleInvalidCharacter.into());
+    }
+
+    #[test]
+    fn tags_are_lowercase_slugs() {
+        validate_tag("solana-2025").unwrap();
+        validate_tag(&"a".repeat(MAX_TAG_LEN)).unwrap();
+
+        for tag in [
+            String::new(),
+            "a".repeat(MAX_TAG_LEN + 1),
+            "Solana".into(),
+            "solana_news".into(),
+            "solana news".into(),
+        ] {
+            assert_eq!(
+                validate_tag(&tag).unwrap_err(),
+                CustomError::InvalidTag.into(),
+                "{tag:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn comments_need_a_body_or_cid() {
+        validate_comment("gm", "").unwrap();
+        validate_comment("", "bafy").unwrap();
+        validate_comment(&"a".repeat(MAX_COMMENT_LEN), &"b".repeat(MAX_IPFS_HASH_LEN)).unwrap();
+
+        let err = |body: &str, body_cid: &str| validate_comment(body, body_cid).unwrap_err();
+        assert_eq!(err("", ""), CustomError::EmptyComment.into());
+        assert_eq!(
+            err(&"a".repeat(MAX_COMMENT_LEN + 1), ""),
+            CustomError::CommentTooLong.into()
+        );
+        assert_eq!(
+            err("", &"b".repeat(MAX_IPFS_HASH_LEN + 1)),
+            CustomError::IpfsHashTooLong.into()
+        );
+    }
+
+    #[test]
+    fn tips_split_between_author_and_pool() {
+        assert_eq!(split_tip(None, 0, 1_000), (1_000, 0));
+        assert_eq!(split_tip(None, 250, 1_000), (975, 25));
+        assert_eq!(split_tip(None, 250, 39), (39, 0));
+        // token pools can't take a cut of a SOL tip
+        assert_eq!(
+            split_tip(Some(Pubkey::new_unique()), 250, 1_000),
+            (1_000, 0)
+        );
+
+        for amount in [1, 7, 9_999, u64::MAX] {
+            let (author_share, platform_cut) = split_tip(None, MAX_TIP_FEE_BPS, amount);
+            assert_eq!(author_share + platform_cut, amount);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+// Operator side of the commit/reveal draw. A round's seed is committed as
+// sha256(seed) before its first entry, lock_draw picks a future slot once
+// entries are locked, and declare_winner reveals the seed. The program mixes
+// that slot's hash into the round entropy, so the winners can only be worked
+// out here after the slot has passed.
+import { BN, Program } from '@coral-xyz/anchor'
+import { AccountMeta, ComputeBudgetProgram, PublicKey, SYSVAR_SLOT_HASHES_PUBKEY } from '@solana/web3.js'
+import type { Counter } from '../target/types/counter'
+import {
+  BLOG_ENTRY_SIZE,
+  DEFAULT_POOL_ID,
+  getPoolConfigPda,
+  getPrizeClaimPda,
+  getRoundPda,
+  getTreasuryPda,
+  getWeeklyPoolPda,
+} from './counter-exports'
+
+// Returns the seed for a round, creating it when `create` is set. The same
+// seed must come back at reveal time, so it has to be stored or derived.
+export type DrawSeedSource = (pool: PublicKey, roundId: BN, create: boolean) => Promise<Uint8Array>
+
+// Slots the SlotHashes sysvar keeps; a locked draw must be revealed within them.
+export const SLOT_HASHES_DEPTH = 512
+
+export type DrawStep =
+  | { step: 'committed'; signature: string }
+  | { step: 'cancelled'; signature: string }
+  | { step: 'waiting'; reason: string; nextDrawIn?: number }
+  | { step: 'declared'; signature: string; rolledOver: boolean }
+
+async function sha256(...parts: Uint8Array[]) {
+  const data = new Uint8Array(parts.reduce((len, part) => len + part.length, 0))
+  let offset = 0
+  for (const part of parts) {
+    data.set(part, offset)
+    offset += part.length
+  }
+  return new Uint8Array(await globalThis.crypto.subtle.digest('SHA-256', data))
+}
+
+function u64Bytes(value: BN | number) {
+  return new Uint8Array(new BN(value).toArrayLike(Buffer, 'le', 8))
+}
+
+export function drawCommitment(seed: Uint8Array) {
+  return sha256(seed)
+}
+
+// Seed derived as HMAC-SHA256(secret, pool || round_id), so a stateless
+// operator such as a cron job can re-derive it for the reveal.
+export async function deriveDrawSeed(secret: Uint8Array, pool: PublicKey, roundId: BN) {
+  const key = await globalThis.crypto.subtle.importKey('raw', secret, { name: 'HMAC', hash: 'SHA-256' }, false, [
+    'sign',
+  ])
+  const message = new Uint8Array([...pool.toBytes(), ...u64Bytes(roundId)])
+  return new Uint8Array(await globalThis.crypto.subtle.sign('HMAC', key, message))
+}
+
+// Mirrors slot_hash_at_or_after in the program: SlotHashes is a u64 length
+// followed by (slot, hash) pairs, newest first.
+export function slotHashAtOrAfter(data: Buffer, target: BN) {
+  const len = Number(data.readBigUInt64LE(0))
+  let found: Uint8Array | null = null
+  for (let i = 0; i < len; i++) {
+    const offset = 8 + i * 40
+    const slot = new BN(data.subarray(offset, offset + 8), 'le')
+    if (slot.lt(target)) {
+      if (!found) throw new Error('The reveal slot has not been reached yet')
+      return found
+    }
+    found = new Uint8Array(data.subarray(offset + 8, offset + 40))
+  }
+  throw new Error("The reveal slot's hash has expired; the round has to be cancelled")
+}
+
+// Mirrors winning_ticket in the program.
+export async function winningTicket(
+  seed: Uint8Array,
+  entropy: Uint8Array,
+  slot: number,
+  attempt: number,
+  ticketCount: BN,
+) {
+  const digest = await sha256(seed, entropy, u64Bytes(slot), u64Bytes(attempt))
+  return new BN(digest.subarray(0, 8), 'le').mod(ticketCount)
+}
+
+// Works out the remaining accounts for declare_winner the same way the
+// program's find_winner walks them: for each prize slot, every voided range
+// the draw hits for the first time, then the winning entry and its claim.
+export async function buildDrawAccounts(program: Program<Counter>, pool: PublicKey, seed: Uint8Array) {
+  const poolData = await program.account.weeklyPool.fetch(pool)
+  const round = await program.account.roundState.fetch(getRoundPda(pool, poolData.roundId, program.programId))
+  const config = await program.account.poolConfig.fetch(getPoolConfigPda(pool, program.programId))
+
+  const slotHashes = await program.provider.connection.getAccountInfo(SYSVAR_SLOT_HASHES_PUBKEY)
+  if (!slotHashes) throw new Error('SlotHashes sysvar not found')
+  const slotHash = slotHashAtOrAfter(slotHashes.data, round.revealSlot)
+  const entropy = await sha256(new Uint8Array(round.entropy), slotHash)
+
+  const prizeCount = Math.min(
+    config.tierSharesBps.filter((bps: number) => bps > 0).length + config.consolationWinners,
+    poolData.totalEntries.toNumber(),
+  )
+
+  const inRound = (account: { pool: PublicKey; roundId: BN }) =>
+    account.pool.equals(pool) && account.roundId.eq(poolData.roundId)
+  const entries = (await program.account.blogEntryState.all([{ dataSize: BLOG_ENTRY_SIZE }])).filter((e) =>
+    inRound(e.account),
+  )
+  const voids = (await program.account.voidTicket.all()).filter((v) => inRound(v.account))
+
+  const metas: AccountMeta[] = []
+  const skipped: [BN, BN][] = []
+  const contains = (start: BN, end: BN, ticket: BN) => ticket.gte(start) && ticket.lt(end)
+
+  for (let slot = 0; slot < prizeCount; slot++) {
+    for (let attempt = 0; ; attempt++) {
+      const ticket = await winningTicket(seed, entropy, slot, attempt, round.ticketCount)
+      if (skipped.some(([start, end]) => contains(start, end, ticket))) continue
+
+      const voided = voids.find((v) => contains(v.account.ticketStart, v.account.ticketEnd, ticket))
+      if (voided) {
+        metas.push({ pubkey: voided.publicKey, isSigner: false, isWritable: false })
+        skipped.push([voided.account.ticketStart, voided.account.ticketEnd])
+        continue
+      }
+
+      const entry = entries.find((e) =>
+        contains(e.account.ticketStart, e.account.ticketStart.add(e.account.tickets), ticket),
+      )
+      if (!entry) throw new Error(`No entry holds ticket ${ticket.toString()}`)
+      metas.push({ pubkey: entry.publicKey, isSigner: false, isWritable: false })
+      metas.push({ pubkey: getPrizeClaimPda(entry.publicKey, program.programId), isSigner: false, isWritable: true })
+      skipped.push([entry.account.ticketStart, entry.account.ticketStart.add(entry.account.tickets)])
+      break
+    }
+  }
+  return metas
+}
+
+async function commitRound(program: Program<Counter>, pool: PublicKey, roundId: BN, seedFor: DrawSeedSource) {
+  const seed = await seedFor(pool, roundId, true)
+  return program.methods
+    .commitDraw(Array.from(await drawCommitment(seed)))
+    .accountsPartial({
+      weeklyPool: pool,
+      poolConfig: getPoolConfigPda(pool, program.programId),
+      round: getRoundPda(pool, roundId, program.programId),
+      authority: program.provider.publicKey,
+    })
+    .rpc()
+}
+
+async function waitForSlot(program: Program<Counter>, slot: BN) {
+  // SlotHashes only has a slot's hash once a later slot is being processed
+  while (new BN(await program.provider.connection.getSlot('confirmed')).lte(slot)) {
+    await new Promise((resolve) => setTimeout(resolve, 400))
+  }
+}
+
+// Moves a pool's draw one stage forward: commits the open round if it has no
+// commitment yet, cancels it if its reveal expired, or once entries are
+// locked, locks the reveal slot, waits for it, declares the winners and
+// commits the next round. Must run as the pool creator or a registered crank.
+export async function advanceDraw(
+  program: Program<Counter>,
+  seedFor: DrawSeedSource,
+  poolId: BN = DEFAULT_POOL_ID,
+): Promise<DrawStep> {
+  const pool = getWeeklyPoolPda(poolId, program.programId)
+  const poolData = await program.account.weeklyPool.fetch(pool)
+  const config = await program.account.poolConfig.fetch(getPoolConfigPda(pool, program.programId))
+  const roundPda = getRoundPda(pool, poolData.roundId, program.programId)
+  let round = await program.account.roundState.fetch(roundPda)
+
+  if (!round.drawCommitment) {
+    if (!round.ticketCount.isZero()) {
+      return { step: 'waiting', reason: 'Round has entries but no commitment; it can only be cancelled' }
+    }
+    return { step: 'committed', signature: await commitRound(program, pool, poolData.roundId, seedFor) }
+  }
+
+  // a lock whose slot hash has expired can never be revealed, so give the
+  // round up and refund it rather than leave the pool stuck
+  if (!round.revealSlot.isZero()) {
+    const slot = await program.provider.connection.getSlot('confirmed')
+    if (round.revealSlot.addn(SLOT_HASHES_DEPTH).lten(slot)) {
+      const signature = await program.methods
+        .cancelRound()
+        .accountsPartial({
+          weeklyPool: pool,
+          poolConfig: getPoolConfigPda(pool, program.programId),
+          currentRound: roundPda,
+          nextRound: getRoundPda(pool, poolData.roundId.addn(1), program.programId),
+          caller: program.provider.publicKey,
+        })
+        .rpc()
+      await commitRound(program, pool, poolData.roundId.addn(1), seedFor)
+      return { step: 'cancelled', signature }
+    }
+  }
+
+  if (poolData.totalEntries.isZero()) {
+    return { step: 'waiting', reason: 'No entries in pool' }
+  }
+  const now = Math.floor(Date.now() / 1000)
+  const sinceLastSpin = now - poolData.lastSpinTimestamp.toNumber()
+  if (sinceLastSpin < config.cooldownSecs.toNumber()) {
+    return { step: 'waiting', reason: 'Cooldown active', nextDrawIn: config.cooldownSecs.toNumber() - sinceLastSpin }
+  }
+
+  const seed = await seedFor(pool, poolData.roundId, false)
+  const rolledOver = poolData.totalPool.lt(config.minPot) || poolData.totalEntries.lt(config.minEntries)
+  let remainingAccounts: AccountMeta[] = []
+  if (!rolledOver) {
+    if (round.revealSlot.isZero()) {
+      await program.methods
+        .lockDraw()
+        .accountsPartial({
+          weeklyPool: pool,
+          poolConfig: getPoolConfigPda(pool, program.programId),
+          round: roundPda,
+          authority: program.provider.publicKey,
+        })
+        .rpc()
+      round = await program.account.roundState.fetch(roundPda)
+    }
+    await waitForSlot(program, round.revealSlot)
+    remainingAccounts = await buildDrawAccounts(program, pool, seed)
+  }
+
+  const signature = await program.methods
+    .declareWinner(Array.from(seed))
+    .accountsPartial({
+      weeklyPool: pool,
+      poolConfig: getPoolConfigPda(pool, program.programId),
+      currentRound: roundPda,
+      nextRound: getRoundPda(pool, poolData.roundId.addn(1), program.programId),
+      treasury: getTreasuryPda(pool, program.programId),
+      slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+      authority: program.provider.publicKey,
+    })
+    .remainingAccounts(remainingAccounts)
+    .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 })])
+    .rpc()
+
+  // entries for the next round are refused until it is committed
+  await commitRound(program, pool, poolData.roundId.addn(1), seedFor)
+  return { step: 'declared', signature, rolledOver }
+}
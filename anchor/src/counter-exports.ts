@@ -1,5 +1,5 @@
 // Here we export some useful types and functions for interacting with the Anchor program.
-import { AnchorProvider, Program } from '@coral-xyz/anchor'
+import { AnchorProvider, BN, Program } from '@coral-xyz/anchor'
 import { Cluster, PublicKey } from '@solana/web3.js'
 import CounterIDL from '../target/idl/counter.json'
 import type { Counter } from '../target/types/counter'
@@ -28,3 +28,61 @@ export function getCounterProgramId(cluster: Cluster) {
       return COUNTER_PROGRAM_ID
   }
 }
+
+// Pools are keyed by a u64 pool_id; the app reads and enters this one.
+export const DEFAULT_POOL_ID = new BN(0)
+
+// 8 + BlogEntryState::INIT_SPACE. Unmigrated legacy entries are smaller and
+// don't decode as BlogEntryState, so filter on this when listing entries.
+export const BLOG_ENTRY_SIZE = 543
+
+function u64Seed(value: BN | number) {
+  return new BN(value).toArrayLike(Buffer, 'le', 8)
+}
+
+function findPda(seeds: Buffer[], programId: PublicKey) {
+  return PublicKey.findProgramAddressSync(seeds, programId)[0]
+}
+
+export function getWeeklyPoolPda(poolId: BN | number = DEFAULT_POOL_ID, programId = COUNTER_PROGRAM_ID) {
+  return findPda([Buffer.from('weekly_pool_data'), u64Seed(poolId)], programId)
+}
+
+// The singleton pool from before pools were keyed by pool_id. Its creator
+// drains it into a keyed pool with migrateLegacyPool.
+export function getLegacyWeeklyPoolPda(programId = COUNTER_PROGRAM_ID) {
+  return findPda([Buffer.from('weekly_pool_data')], programId)
+}
+
+export function getPoolConfigPda(pool: PublicKey, programId = COUNTER_PROGRAM_ID) {
+  return findPda([Buffer.from('pool_config'), pool.toBuffer()], programId)
+}
+
+export function getRoundPda(pool: PublicKey, roundId: BN | number, programId = COUNTER_PROGRAM_ID) {
+  return findPda([Buffer.from('round'), pool.toBuffer(), u64Seed(roundId)], programId)
+}
+
+export function getTreasuryPda(pool: PublicKey, programId = COUNTER_PROGRAM_ID) {
+  return findPda([Buffer.from('treasury'), pool.toBuffer()], programId)
+}
+
+export function getVoidTicketPda(
+  pool: PublicKey,
+  roundId: BN | number,
+  ticketStart: BN | number,
+  programId = COUNTER_PROGRAM_ID,
+) {
+  return findPda([Buffer.from('void_ticket'), pool.toBuffer(), u64Seed(roundId), u64Seed(ticketStart)], programId)
+}
+
+export function getPrizeClaimPda(entry: PublicKey, programId = COUNTER_PROGRAM_ID) {
+  return findPda([Buffer.from('prize_claim'), entry.toBuffer()], programId)
+}
+
+export function getAuthorProfilePda(owner: PublicKey, programId = COUNTER_PROGRAM_ID) {
+  return findPda([Buffer.from('author'), owner.toBuffer()], programId)
+}
+
+export function getPostPda(owner: PublicKey, postIndex: BN | number, programId = COUNTER_PROGRAM_ID) {
+  return findPda([Buffer.from('post'), owner.toBuffer(), u64Seed(postIndex)], programId)
+}
@@ -1 +1,2 @@
 export * from './counter-exports'
+export * from './counter-draw'
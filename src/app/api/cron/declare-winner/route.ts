@@ -1,7 +1,15 @@
 import { NextResponse } from 'next/server'
-import { Connection, Keypair, PublicKey } from '@solana/web3.js'
+import { Connection, Keypair } from '@solana/web3.js'
 import { AnchorProvider } from '@coral-xyz/anchor'
-import { getCounterProgram, getCounterProgramId } from '@project/anchor'
+import {
+  advanceDraw,
+  DEFAULT_POOL_ID,
+  deriveDrawSeed,
+  type DrawSeedSource,
+  getCounterProgram,
+  getCounterProgramId,
+  getWeeklyPoolPda,
+} from '@project/anchor'
 import NodeWallet from '@coral-xyz/anchor/dist/cjs/nodewallet'
 
 export async function GET(request: Request) {
@@ -32,101 +40,33 @@ export async function GET(request: Request) {
     const programId = getCounterProgramId('devnet')
     const program = getCounterProgram(provider, programId)
 
-    const [weeklyPoolPda] = PublicKey.findProgramAddressSync([Buffer.from('weekly_pool_data')], programId)
-    const poolData = await program.account.weeklyPool.fetch(weeklyPoolPda)
-
-    const now = Math.floor(Date.now() / 1000)
-    const cooldownSeconds = 7 * 24 * 60 * 60
-    const timeSinceLastSpin = now - Number(poolData.lastSpinTimestamp)
-
-    if (timeSinceLastSpin < cooldownSeconds) {
-      console.log('Cooldown active')
-      return NextResponse.json({
-        message: 'Cooldown active',
-        nextDrawIn: cooldownSeconds - timeSinceLastSpin,
-        lastSpinTimestamp: Number(poolData.lastSpinTimestamp),
-      })
+    const drawSecret = process.env.DRAW_SEED_SECRET
+    if (!drawSecret) {
+      throw new Error('DRAW_SEED_SECRET not configured')
     }
+    // seeds are derived from the secret, so nothing has to be kept between runs
+    const secret = new TextEncoder().encode(drawSecret)
+    const seedFor: DrawSeedSource = (pool, roundId) => deriveDrawSeed(secret, pool, roundId)
 
-    if (Number(poolData.totalEntries) === 0) {
-      console.log('No entries in pool')
-      return NextResponse.json({
-        message: 'No entries in pool',
-        totalEntries: 0,
-      })
-    }
+    const poolData = await program.account.weeklyPool.fetch(getWeeklyPoolPda(DEFAULT_POOL_ID, programId))
+    const result = await advanceDraw(program, seedFor, DEFAULT_POOL_ID)
+    console.log('Draw step:', result)
 
-    const allBlogs = await program.account.blogEntryState.all()
-    if (allBlogs.length === 0) {
-      console.log('No blog entries found')
+    if (result.step === 'waiting') {
       return NextResponse.json({
-        message: 'No blog entries found',
-        totalEntries: 0,
-      })
-    }
-
-    // Select random winner
-    const randomIndex = Math.floor(Math.random() * allBlogs.length)
-    const winnerBlog = allBlogs[randomIndex]
-
-    console.log('Selected winner:', {
-      title: winnerBlog.account.title,
-      owner: winnerBlog.account.owner.toBase58(),
-      randomNumber: winnerBlog.account.randomNumber,
-    })
-
-    // Call declare_winner
-    const signature = await program.methods
-      .declareWinner()
-      .accounts({
-        winnerBlog: winnerBlog.publicKey,
-        winner: winnerBlog.account.owner,
-        creatorWallet: poolData.creator,
-      })
-      .rpc({
-        commitment: 'confirmed',
-        skipPreflight: false,
+        message: result.reason,
+        nextDrawIn: result.nextDrawIn,
+        lastSpinTimestamp: Number(poolData.lastSpinTimestamp),
       })
-
-    // Poll for confirmation instead of using WebSocket subscription
-    let confirmed = false
-    let attempts = 0
-    const maxAttempts = 30 // 30 seconds timeout
-
-    while (!confirmed && attempts < maxAttempts) {
-      attempts++
-      try {
-        const status = await connection.getSignatureStatus(signature)
-        if (status?.value?.confirmationStatus === 'confirmed' || status?.value?.confirmationStatus === 'finalized') {
-          confirmed = true
-          console.log('Transaction confirmed after', attempts, 'attempts')
-          break
-        }
-      } catch (err) {
-        console.log('Polling attempt', attempts, 'failed:', err)
-      }
-
-      // Wait 1 second before next attempt
-      await new Promise((resolve) => setTimeout(resolve, 1000))
-    }
-
-    if (!confirmed) {
-      console.warn('Transaction sent but confirmation timed out:', signature)
     }
 
-    console.log('Winner declared successfully:', signature)
-
     return NextResponse.json({
       success: true,
-      signature,
-      confirmed,
-      winner: {
-        title: winnerBlog.account.title,
-        owner: winnerBlog.account.owner.toBase58(),
-        randomNumber: winnerBlog.account.randomNumber,
-      },
+      step: result.step,
+      signature: result.signature,
+      rolledOver: result.step === 'declared' ? result.rolledOver : undefined,
       prizePool: Number(poolData.totalPool),
-      timestamp: now,
+      timestamp: Math.floor(Date.now() / 1000),
     })
   } catch (error) {
     console.error('Error in declare-winner cron:', error)
@@ -1,6 +1,17 @@
 'use client'
 
-import { getCounterProgram, getCounterProgramId } from '@project/anchor'
+import {
+  BLOG_ENTRY_SIZE,
+  DEFAULT_POOL_ID,
+  getAuthorProfilePda,
+  getCounterProgram,
+  getCounterProgramId,
+  getLegacyWeeklyPoolPda,
+  getPoolConfigPda,
+  getPostPda,
+  getRoundPda,
+  getWeeklyPoolPda,
+} from '@project/anchor'
 import { useConnection } from '@solana/wallet-adapter-react'
 import { PublicKey } from '@solana/web3.js'
 import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query'
@@ -10,7 +21,6 @@ import { useAnchorProvider } from '../solana/solana-provider'
 import { useTransactionToast } from '../use-transaction-toast'
 import { toast } from 'sonner'
 import { BN } from 'bn.js'
-import type { BlogProgramAccount } from '../lucky-wheel'
 
 interface CreateEntryArgs {
   title: string
@@ -31,7 +41,7 @@ export function useCounterProgram() {
 
   const accounts = useQuery({
     queryKey: ['blogEntry', 'all', { cluster }],
-    queryFn: () => program.account.blogEntryState.all(),
+    queryFn: () => program.account.blogEntryState.all([{ dataSize: BLOG_ENTRY_SIZE }]),
   })
 
   const getProgramAccount = useQuery({
@@ -42,7 +52,7 @@ export function useCounterProgram() {
   const weeklyPool = useQuery({
     queryKey: ['weekly-pool', { cluster }],
     queryFn: async () => {
-      const [weeklyPoolPda] = PublicKey.findProgramAddressSync([Buffer.from('weekly_pool_data')], programId)
+      const weeklyPoolPda = getWeeklyPoolPda(DEFAULT_POOL_ID, programId)
       try {
         const account = await program.account.weeklyPool.fetch(weeklyPoolPda)
         return { account, publicKey: weeklyPoolPda }
@@ -56,55 +66,42 @@ export function useCounterProgram() {
   const createEntry = useMutation<string, Error, CreateEntryArgs>({
     mutationKey: [`blogEntry`, `create`, { cluster }],
     mutationFn: async ({ title, ipfsHash, poolContribution }) => {
-      const [weeklyPoolPDA] = PublicKey.findProgramAddressSync([Buffer.from('weekly_pool_data')], program.programId)
-      const info = await connection.getAccountInfo(weeklyPoolPDA)
-
-      const blogSeeds = [Buffer.from(title), provider.wallet.publicKey.toBuffer()]
-      const [blogPda] = PublicKey.findProgramAddressSync(blogSeeds, program.programId)
-
       if (title.length > 50) {
         throw new Error('Title must be at most 50 characters long')
       }
 
-      const isBlogTitleAlredyInUse = !!(await connection.getAccountInfo(blogPda))
-
-      if (isBlogTitleAlredyInUse) {
-        throw new Error('Blog title already in use by this wallet')
+      const owner = provider.wallet.publicKey
+      const weeklyPoolPda = getWeeklyPoolPda(DEFAULT_POOL_ID, program.programId)
+      const pool = await program.account.weeklyPool.fetchNullable(weeklyPoolPda)
+      if (!pool) {
+        throw new Error('The weekly pool has not been initialized yet')
       }
 
-      if (!info) {
-        try {
-          await program.methods.initializePool().rpc()
-        } catch (error) {
-          console.log(error)
-          toast.warning('Pool account already exists')
-        }
+      // entries are seeded by the author's post count, so a profile is needed first
+      const authorProfile = getAuthorProfilePda(owner, program.programId)
+      let profile = await program.account.authorProfile.fetchNullable(authorProfile)
+      if (!profile) {
+        await program.methods.createProfile('', '').accountsPartial({ authorProfile, owner }).rpc()
+        profile = await program.account.authorProfile.fetch(authorProfile)
       }
 
-      return program.methods.createBlogEntry(title, ipfsHash, new BN(poolContribution)).rpc()
-
-      /**
-       * program.methods.createBlogEntry(title, ipfsHash, new BN(poolContribution)).rpc() 
-       * 
-       * IS SAME AS
-       * 
-      const [weeklyPool] = PublicKey.findProgramAddressSync([Buffer.from("weekly_pool")], program.programId)
-
-    return await program.methods
-      .createBlogEntry(title, ipfsHash, new anchor.BN(poolContribution))
-      .accounts({
-        blogEntry: PublicKey.findProgramAddressSync(
-          [Buffer.from("blog"), Buffer.from(title), provider.wallet.publicKey.toBuffer()],
-          program.programId
-        )[0],
-        weeklyPool,
-        owner: provider.wallet.publicKey,
-        systemProgram: SystemProgram.programId,
-      })
-      .rpc()
-  },
-
-       */
+      return program.methods
+        .createBlogEntry(title, ipfsHash, new BN(poolContribution))
+        .accountsPartial({
+          authorProfile,
+          blogEntry: getPostPda(owner, profile.postCount, program.programId),
+          owner,
+          weeklyPool: weeklyPoolPda,
+          poolConfig: getPoolConfigPda(weeklyPoolPda, program.programId),
+          round: getRoundPda(weeklyPoolPda, pool.roundId, program.programId),
+          mint: null,
+          ownerTokenAccount: null,
+          vault: null,
+          tokenProgram: null,
+          campaign: null,
+          campaignMatch: null,
+        })
+        .rpc()
     },
     onSuccess(signatute) {
       transactionToast(signatute)
@@ -120,33 +117,25 @@ export function useCounterProgram() {
     },
   })
 
-  const declareWinner = useMutation<string, Error, BlogProgramAccount>({
-    mutationKey: [`declareWinner`, { cluster }],
-    mutationFn: async (blog: BlogProgramAccount) => {
-      const [weeklyPoolPda] = PublicKey.findProgramAddressSync([Buffer.from('weekly_pool_data')], program.programId)
-      const poolData = await weeklyPool.refetch()
-
-      const creatorWallet = poolData.data?.account.creator
-      if (!creatorWallet) {
-        throw new Error('Creator wallet not found')
-      }
-
+  // Moves the pot of the pre-upgrade singleton pool into the default pool.
+  const migrateLegacyPool = useMutation<string, Error>({
+    mutationKey: ['migrateLegacyPool', { cluster }],
+    mutationFn: async () => {
       return program.methods
-        .declareWinner()
-        .accounts({
-          winnerBlog: blog.publicKey,
-          winner: blog.account.owner,
-          creatorWallet: creatorWallet,
+        .migrateLegacyPool()
+        .accountsPartial({
+          legacyPool: getLegacyWeeklyPoolPda(program.programId),
+          weeklyPool: getWeeklyPoolPda(DEFAULT_POOL_ID, program.programId),
+          creator: provider.wallet.publicKey,
         })
         .rpc()
     },
     onSuccess(signature) {
       transactionToast(signature)
       weeklyPool.refetch()
-      accounts.refetch()
     },
     onError(error) {
-      toast.error(`Error declaring winner: ${error.message}`)
+      toast.error(`Error migrating legacy pool: ${error.message}`)
     },
   })
 
@@ -156,7 +145,7 @@ export function useCounterProgram() {
     accounts,
     getProgramAccount,
     createEntry,
-    declareWinner,
+    migrateLegacyPool,
     weeklyPool,
   }
 }